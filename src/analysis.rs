@@ -0,0 +1,288 @@
+use std::collections::{ BTreeSet, HashMap, HashSet };
+
+use crate::graph::Graph;
+
+/// A strongly connected group of nodes: every node in it can reach every other through
+/// the streams in `edges`. On a plant this is a recycle loop (or a modelling mistake).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    /// Node ids in `graph.nodes` order.
+    pub nodes: Vec<usize>,
+    /// Ids of the edges running between members, in `graph.edges` order.
+    pub edges: Vec<u64>,
+}
+
+/// Every cycle in the graph, grouped into strongly connected components (Tarjan).
+///
+/// Components of a single node only count when the node feeds itself. Loops are ordered
+/// by their first node's position in `graph.nodes`. Edges whose endpoints are missing
+/// are ignored.
+pub fn find_loops(graph: &Graph) -> Vec<Loop> {
+    let index_of: HashMap<usize, usize> = graph.nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); graph.nodes.len()];
+    for edge in &graph.edges {
+        if
+            let (Some(&from), Some(&to)) = (
+                index_of.get(&(edge.from as usize)),
+                index_of.get(&(edge.to as usize)),
+            )
+        {
+            successors[from].push(to);
+        }
+    }
+
+    let components = tarjan(&successors);
+    let mut loops = Vec::new();
+    for mut component in components {
+        component.sort();
+        let members: HashSet<usize> = component
+            .iter()
+            .map(|&i| graph.nodes[i].id)
+            .collect();
+        let edges: Vec<u64> = graph.edges
+            .iter()
+            .filter(|e| members.contains(&(e.from as usize)) && members.contains(&(e.to as usize)))
+            .map(|e| e.id)
+            .collect();
+        if component.len() == 1 && edges.is_empty() {
+            continue;
+        }
+        loops.push(Loop {
+            nodes: component
+                .iter()
+                .map(|&i| graph.nodes[i].id)
+                .collect(),
+            edges,
+        });
+    }
+    loops.sort_by_key(|l| l.nodes.first().and_then(|id| index_of.get(id)).copied());
+    loops
+}
+
+/// Iterative Tarjan over node indices, so deep chains cannot overflow the stack.
+fn tarjan(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = successors.len();
+    let mut index = vec![usize::MAX; n];
+    let mut lowlink = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        // (node, position of the next successor to visit)
+        let mut work = vec![(root, 0)];
+        while let Some(&mut (v, ref mut next)) = work.last_mut() {
+            if *next == 0 && index[v] == usize::MAX {
+                index[v] = next_index;
+                lowlink[v] = next_index;
+                next_index += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if let Some(&w) = successors[v].get(*next) {
+                *next += 1;
+                if index[w] == usize::MAX {
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    lowlink[v] = lowlink[v].min(index[w]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[v]);
+            }
+            if lowlink[v] == index[v] {
+                let mut component = Vec::new();
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Node indices (into `graph.nodes`) ordered upstream to downstream.
+///
+/// Kahn's algorithm, always taking the ready node with the smallest id, so nodes without
+/// producers (`source` units) come first. When only cycles remain, the loop is entered
+/// at the smallest-id node fed by something already placed (or the smallest id overall)
+/// and its remaining inputs are ignored, which keeps the result deterministic.
+pub fn flow_order(graph: &Graph) -> Vec<usize> {
+    let index_of: HashMap<usize, usize> = graph.nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+    let n = graph.nodes.len();
+    let mut successors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut pending = vec![0usize; n];
+    for edge in &graph.edges {
+        let from = index_of.get(&(edge.from as usize));
+        let to = index_of.get(&(edge.to as usize));
+        if let (Some(&from), Some(&to)) = (from, to) {
+            successors[from].push(to);
+            predecessors[to].push(from);
+            pending[to] += 1;
+        }
+    }
+
+    let by_id = |i: &usize| graph.nodes[*i].id;
+    let mut placed = vec![false; n];
+    let mut ready: BTreeSet<(usize, usize)> = (0..n)
+        .filter(|&i| pending[i] == 0)
+        .map(|i| (by_id(&i), i))
+        .collect();
+    let mut order = Vec::with_capacity(n);
+    while order.len() < n {
+        let next = match ready.pop_first() {
+            Some((_, i)) => i,
+            None => {
+                let remaining = (0..n).filter(|&i| !placed[i]);
+                let entry = remaining
+                    .clone()
+                    .filter(|&i| predecessors[i].iter().any(|&p| placed[p]))
+                    .min_by_key(by_id);
+                match entry.or_else(|| remaining.min_by_key(by_id)) {
+                    Some(i) => i,
+                    None => break,
+                }
+            }
+        };
+        if placed[next] {
+            continue;
+        }
+        placed[next] = true;
+        order.push(next);
+        for &w in &successors[next] {
+            if placed[w] {
+                continue;
+            }
+            pending[w] = pending[w].saturating_sub(1);
+            if pending[w] == 0 {
+                ready.insert((by_id(&w), w));
+            }
+        }
+    }
+    order
+}
+
+/// Node and edge ids that sit on some loop, for highlighting.
+pub fn loop_members(loops: &[Loop]) -> (HashSet<usize>, HashSet<u64>) {
+    let nodes = loops
+        .iter()
+        .flat_map(|l| l.nodes.iter().copied())
+        .collect();
+    let edges = loops
+        .iter()
+        .flat_map(|l| l.edges.iter().copied())
+        .collect();
+    (nodes, edges)
+}
+
+/// `find_loops` and `loop_members` of a graph, kept until the history state changes so
+/// screens can highlight loops on every frame without rerunning Tarjan.
+#[derive(Debug, Default)]
+pub struct LoopCache {
+    state: Option<u64>,
+    pub loops: Vec<Loop>,
+    pub nodes: HashSet<usize>,
+    pub edges: HashSet<u64>,
+}
+
+impl LoopCache {
+    /// Recompute for `graph` unless it was already done at history `state`.
+    pub fn refresh(&mut self, graph: &Graph, state: u64) {
+        if self.state == Some(state) {
+            return;
+        }
+        self.loops = find_loops(graph);
+        (self.nodes, self.edges) = loop_members(&self.loops);
+        self.state = Some(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+    use crate::node_builder::NodeInstance;
+
+    #[test]
+    fn finds_recycle_loops_and_self_loops() {
+        let mut g = Graph::new();
+        for id in 1..=6 {
+            g.add_node(NodeInstance::new(id, "cstr".to_string(), format!("t{}", id)));
+        }
+        let links = [(1, 2), (2, 3), (3, 2), (3, 4), (4, 5), (5, 3), (6, 6), (5, 99)];
+        for (i, (from, to)) in links.iter().enumerate() {
+            g.add_edge(Edge {
+                id: (i as u64) + 1,
+                from: *from,
+                to: *to,
+                label: format!("s{}", i + 1),
+            });
+        }
+
+        let loops = find_loops(&g);
+        assert_eq!(
+            loops,
+            vec![
+                Loop { nodes: vec![2, 3, 4, 5], edges: vec![2, 3, 4, 5, 6] },
+                Loop { nodes: vec![6], edges: vec![7] }
+            ]
+        );
+        let (nodes, edges) = loop_members(&loops);
+        assert!(!nodes.contains(&1));
+        assert!(!edges.contains(&1));
+
+        // The cache only looks again once the history state moves on.
+        let mut cache = LoopCache::default();
+        cache.refresh(&g, 1);
+        assert_eq!(cache.loops, loops);
+        g.edges.retain(|edge| edge.id != 7);
+        cache.refresh(&g, 1);
+        assert!(cache.nodes.contains(&6));
+        cache.refresh(&g, 2);
+        assert!(!cache.nodes.contains(&6) && !cache.edges.contains(&7));
+    }
+
+    #[test]
+    fn flow_order_runs_upstream_to_downstream_and_breaks_loops() {
+        let mut g = Graph::new();
+        // Ids deliberately disagree with the flow: 5 feeds 3 feeds 1, which loops through
+        // 2 and 4 back to itself; 6 stands alone, so it is placed before the loop is broken.
+        for id in 1..=6 {
+            g.add_node(NodeInstance::new(id, "cstr".to_string(), format!("t{}", id)));
+        }
+        let links = [(5, 3), (3, 1), (1, 2), (2, 4), (4, 1)];
+        for (i, (from, to)) in links.iter().enumerate() {
+            g.add_edge(Edge {
+                id: (i as u64) + 1,
+                from: *from,
+                to: *to,
+                label: format!("s{}", i + 1),
+            });
+        }
+        let ids: Vec<usize> = flow_order(&g)
+            .into_iter()
+            .map(|i| g.nodes[i].id)
+            .collect();
+        assert_eq!(ids, vec![5, 3, 6, 1, 2, 4]);
+    }
+}
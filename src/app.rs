@@ -1,13 +1,20 @@
-use crossterm::event::{ KeyCode, KeyEvent };
+use crossterm::event::{ KeyCode, KeyEvent, KeyModifiers };
 use std::collections::HashMap;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CurrentScreen {
     Main,
     Graph,
     GraphEditor,
     NodeEditor,
     EdgeEditor,
+    Canvas,
+    SaveAs,
+    Diagnostics,
+    /// Command palette over `palette_return`.
+    Palette,
+    /// `:` command line over `command_return`.
+    CommandLine,
     Exiting,
 }
 
@@ -16,6 +23,18 @@ pub enum NodeEditorMode {
     Type,
     Label,
     Param,
+    /// Summary of every param before the node is written; Enter confirms.
+    Review,
+}
+
+/// What the review step shows for one param.
+#[derive(Debug, PartialEq)]
+pub enum ParamStatus {
+    /// Set in the editor; the text is what one would type to set it.
+    Set(String),
+    /// Unset, but filled in on export from the template's `source`.
+    Derived(String),
+    Unset,
 }
 
 #[derive(Debug)]
@@ -30,13 +49,30 @@ pub enum InOut {
     To,
 }
 
+/// An entry in the graph editor's list: nodes first, then edges.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphItem {
+    Node(usize),
+    Edge(u64),
+}
+
 #[derive(Debug)]
 pub enum CurrentlyEditing {
     Node(NodeEditorMode),
     Edge(EdgeEditorMode),
 }
 
-use crate::graph::Graph;
+use crate::analysis::LoopCache;
+use crate::canvas::{ MoveTool, Viewport };
+use crate::command::{ self, Outcome };
+use crate::edge::Edge;
+use crate::fuzzy::{ ActionPicker, NodePicker };
+use crate::history::{ History, Transaction };
+use crate::input::TextInput;
+use crate::keymap::{ Action, KeyContext, Keymap };
+use crate::layout::{ apply_layout, ForceLayout, DEFAULT_SEED };
+use crate::graph::{ render_inline_value, Graph, GraphFormat };
+use crate::validate::{ error_count, validate, Diagnostic };
 use crate::node_builder::{
     NodeInstance,
     NodeTypeCatalog,
@@ -46,6 +82,9 @@ use crate::node_builder::{
     ParamType,
     ValueType,
 };
+/// Force-layout iterations run per UI tick while animating.
+const FORCE_STEPS_PER_TICK: usize = 2;
+
 #[derive(Debug)]
 pub struct App {
     pub graph: Graph,
@@ -53,11 +92,60 @@ pub struct App {
     pub node_type_keys: Vec<String>,
     pub node_type_index: usize,
     pub exit: bool,
-    pub label: String,
+    pub label: TextInput,
     pub in_outs: [u64; 2],
     pub current_screen: CurrentScreen,
     pub currently_editing: Option<CurrentlyEditing>,
     pub node_edit: Option<NodeEditState>,
+    /// Position in the graph editor's list (all nodes, then all edges).
+    pub cursor: usize,
+    /// Id of the node being edited in place; `None` while creating a new node.
+    pub editing_node: Option<usize>,
+    /// Search state for the edge editor's From/To endpoints.
+    pub node_picker: NodePicker,
+    pub edge_error: Option<String>,
+    pub viewport: Viewport,
+    pub move_tool: MoveTool,
+    /// Node highlighted on the canvas (Tab cycles), the target of moves.
+    pub canvas_selected: Option<usize>,
+    pub history: History,
+    /// One-line feedback shown in the editor (e.g. what undo just reverted).
+    pub status: Option<String>,
+    /// Where `save` writes; set by `-o`/`-i` or the Save As dialog.
+    pub file_path: Option<String>,
+    /// Format forced by `--format`; otherwise each path's extension decides.
+    pub file_format: Option<GraphFormat>,
+    /// `history.state()` at the last successful save.
+    saved_state: u64,
+    /// Path being typed in the Save As dialog.
+    pub save_path_input: TextInput,
+    /// Screen to return to after the Save As dialog closes.
+    pub save_return: CurrentScreen,
+    /// Exit once the pending Save As succeeds (chosen from the exit prompt).
+    exit_after_save: bool,
+    /// Screen to go back to when leaving the canvas.
+    pub canvas_return: CurrentScreen,
+    /// Result of the last validation run, shown in the diagnostics panel.
+    pub diagnostics: Vec<Diagnostic>,
+    pub diagnostics_index: usize,
+    pub diagnostics_return: CurrentScreen,
+    /// Path whose save was blocked by validation errors; `W` writes it anyway.
+    pub pending_save: Option<String>,
+    /// Key bindings per screen; footers are generated from it too.
+    pub keymap: Keymap,
+    /// Query and selection of the command palette.
+    pub palette: ActionPicker,
+    /// Screen the palette was opened from; its actions are the ones listed.
+    pub palette_return: CurrentScreen,
+    /// Text typed after `:`.
+    pub command_input: TextInput,
+    pub command_return: CurrentScreen,
+    /// Force-directed layout being animated on the canvas, advanced by `update`.
+    pub force_layout: Option<ForceLayout>,
+    /// Nodes as they were when the animation started, to record it as one undo step.
+    force_before: Vec<NodeInstance>,
+    /// Recycle loops of `graph`, brought up to date by `refresh_loops` before drawing.
+    pub loops: LoopCache,
 }
 
 impl App {
@@ -73,289 +161,885 @@ impl App {
             node_type_keys,
             node_type_index: 0,
             exit: false,
-            label: String::new(),
+            label: TextInput::default(),
             in_outs: [0, 0],
             current_screen: CurrentScreen::Main,
             currently_editing: None,
             node_edit: None,
+            cursor: 0,
+            editing_node: None,
+            node_picker: NodePicker::default(),
+            edge_error: None,
+            viewport: Viewport::default(),
+            move_tool: MoveTool::default(),
+            canvas_selected: None,
+            history: History::default(),
+            status: None,
+            file_path: None,
+            file_format: None,
+            saved_state: 0,
+            save_path_input: TextInput::default(),
+            save_return: CurrentScreen::Main,
+            exit_after_save: false,
+            canvas_return: CurrentScreen::Graph,
+            diagnostics: Vec::new(),
+            diagnostics_index: 0,
+            diagnostics_return: CurrentScreen::GraphEditor,
+            pending_save: None,
+            keymap: Keymap::default(),
+            palette: ActionPicker::default(),
+            palette_return: CurrentScreen::Main,
+            command_input: TextInput::default(),
+            command_return: CurrentScreen::GraphEditor,
+            force_layout: None,
+            force_before: Vec::new(),
+            loops: LoopCache::default(),
         }
     }
 
+    /// Advance a running force layout by a few iterations and show the new positions. The
+    /// view stays put while it runs; it is fitted when the layout starts and finishes.
     pub fn update(&mut self) {
-        todo!();
+        let Some(layout) = self.force_layout.as_mut() else {
+            return;
+        };
+        for _ in 0..FORCE_STEPS_PER_TICK {
+            layout.step();
+        }
+        let done = layout.is_done();
+        self.show_force_positions();
+        if done {
+            self.finish_force_layout();
+        }
     }
 
     pub fn on_key(&mut self, key: KeyEvent) {
+        // Status messages describe the previous action only.
+        self.status = None;
+        let context = KeyContext::for_screen(self.current_screen);
+        let action = self.keymap.action(context, &key);
+        // Any key settles a running force layout where it is before doing its own thing.
+        self.finish_force_layout();
         // Handle screen navigation and business logic
         match self.current_screen {
-            CurrentScreen::Main => {
-                match key.code {
-                    KeyCode::Char('g') | KeyCode::Char('G') => {
-                        self.current_screen = CurrentScreen::Graph;
-                    }
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        self.current_screen = CurrentScreen::Exiting;
-                    }
-                    _ => {}
-                }
-            }
-            CurrentScreen::Graph => {
-                match key.code {
-                    KeyCode::Char('e') | KeyCode::Char('E') => {
-                        self.current_screen = CurrentScreen::GraphEditor;
-                    }
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        self.current_screen = CurrentScreen::Main;
-                    }
-                    _ => {}
-                }
-            }
-            CurrentScreen::GraphEditor => {
-                match key.code {
-                    KeyCode::Char('n') | KeyCode::Char('N') => {
-                        self.start_node_editor();
-                    }
-                    KeyCode::Char('e') | KeyCode::Char('E') => {
-                        self.label.clear();
-                        self.in_outs = [0, 0];
-                        self.currently_editing = Some(
-                            CurrentlyEditing::Edge(EdgeEditorMode::Label)
-                        );
-                        self.current_screen = CurrentScreen::EdgeEditor;
-                    }
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        self.current_screen = CurrentScreen::Graph;
-                    }
-                    _ => {}
-                }
-            }
             CurrentScreen::NodeEditor => {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        self.label.clear();
-                        self.currently_editing = None;
-                        self.current_screen = CurrentScreen::GraphEditor;
-                        self.node_edit = None;
-                    }
-                    KeyCode::Enter => {
+                match action {
+                    Some(Action::Confirm) => {
                         match &self.currently_editing {
                             Some(CurrentlyEditing::Node(NodeEditorMode::Type)) => {
                                 self.select_current_type();
                             }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Review)) => {
+                                self.finalize_node_edit();
+                            }
                             _ => {
                                 self.advance_node_editor();
                             }
                         }
                     }
-                    KeyCode::Up => {
-                        if
-                            matches!(
-                                self.currently_editing,
-                                Some(CurrentlyEditing::Node(NodeEditorMode::Type))
-                            )
-                        {
-                            self.move_type_selection(-1);
+                    Some(Action::Up) => {
+                        match &self.currently_editing {
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Type)) => {
+                                self.move_type_selection(-1);
+                            }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => {
+                                self.jump_param(-1);
+                            }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Review)) => {
+                                self.move_review_selection(-1);
+                            }
+                            _ => {}
                         }
                     }
-                    KeyCode::Down => {
-                        if
-                            matches!(
-                                self.currently_editing,
-                                Some(CurrentlyEditing::Node(NodeEditorMode::Type))
-                            )
-                        {
-                            self.move_type_selection(1);
+                    Some(Action::Down) => {
+                        match &self.currently_editing {
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Type)) => {
+                                self.move_type_selection(1);
+                            }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => {
+                                self.jump_param(1);
+                            }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Review)) => {
+                                self.move_review_selection(1);
+                            }
+                            _ => {}
                         }
                     }
-                    KeyCode::Backspace => {
-                        if let Some(CurrentlyEditing::Node(mode)) = &self.currently_editing {
-                            match mode {
-                                NodeEditorMode::Type => {}
-                                NodeEditorMode::Label => {
-                                    self.label.pop();
-                                }
-                                NodeEditorMode::Param => {
-                                    if let Some(edit) = &mut self.node_edit {
-                                        edit.buffer.pop();
-                                    }
-                                }
+                    Some(Action::NextField) => {
+                        match &self.currently_editing {
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => {
+                                self.cycle_param();
                             }
+                            Some(CurrentlyEditing::Node(NodeEditorMode::Review)) => {
+                                self.edit_reviewed_param();
+                            }
+                            _ => {}
                         }
                     }
-                    KeyCode::Esc => {
-                        self.currently_editing = None;
-                        self.label.clear();
-                        self.current_screen = CurrentScreen::GraphEditor;
-                        self.node_edit = None;
+                    Some(Action::Cancel) => {
+                        self.close_node_editor();
                     }
-                    KeyCode::Char(value) => {
-                        if let Some(CurrentlyEditing::Node(mode)) = &self.currently_editing {
-                            match mode {
-                                NodeEditorMode::Type => {}
-                                NodeEditorMode::Label => {
-                                    self.label.push(value);
-                                }
-                                NodeEditorMode::Param => {
-                                    if let Some(edit) = &mut self.node_edit {
-                                        edit.buffer.push(value);
-                                    }
-                                }
-                            }
+                    Some(action) => {
+                        self.edit_active_input(action);
+                    }
+                    None => {
+                        if let Some(value) = typed_char(&key) {
+                            self.insert_text(&value.to_string());
                         }
                     }
-                    _ => {}
                 }
             }
             CurrentScreen::EdgeEditor => {
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Char('Q') => {
-                        self.label.clear();
-                        self.in_outs = [0, 0];
-                        self.currently_editing = None;
-                        self.current_screen = CurrentScreen::GraphEditor;
-                    }
-                    KeyCode::Enter => {
+                match action {
+                    Some(Action::Confirm) => {
                         if let Some(CurrentlyEditing::Edge(mode)) = &self.currently_editing {
                             match mode {
                                 EdgeEditorMode::Label => {
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::InOuts(InOut::From))
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::InOuts(InOut::From));
                                 }
                                 EdgeEditorMode::InOuts(InOut::From) => {
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::InOuts(InOut::To))
-                                    );
+                                    if self.pick_endpoint(0) {
+                                        self.set_edge_mode(EdgeEditorMode::InOuts(InOut::To));
+                                    }
                                 }
                                 EdgeEditorMode::InOuts(InOut::To) => {
-                                    self.add_edge();
-                                    self.label.clear();
-                                    self.in_outs = [0, 0];
-                                    self.currently_editing = None;
-                                    self.current_screen = CurrentScreen::GraphEditor;
+                                    if self.pick_endpoint(1) {
+                                        self.finalize_edge_edit();
+                                    }
                                 }
                             }
                         }
                     }
-                    KeyCode::Tab => {
+                    Some(Action::NextField) => {
                         if let Some(CurrentlyEditing::Edge(mode)) = &self.currently_editing {
                             match mode {
                                 EdgeEditorMode::Label => {
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::InOuts(InOut::From))
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::InOuts(InOut::From));
                                 }
                                 EdgeEditorMode::InOuts(InOut::From) => {
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::InOuts(InOut::To))
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::InOuts(InOut::To));
                                 }
                                 EdgeEditorMode::InOuts(InOut::To) => {
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::Label)
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::Label);
                                 }
                             }
                         }
                     }
-                    KeyCode::Backspace => {
+                    Some(Action::Up) => {
+                        if
+                            matches!(
+                                self.currently_editing,
+                                Some(CurrentlyEditing::Edge(EdgeEditorMode::InOuts(_)))
+                            )
+                        {
+                            let count = self.node_picker.matches(&self.graph).len();
+                            self.node_picker.move_selection(-1, count);
+                        }
+                    }
+                    Some(Action::Down) => {
+                        if
+                            matches!(
+                                self.currently_editing,
+                                Some(CurrentlyEditing::Edge(EdgeEditorMode::InOuts(_)))
+                            )
+                        {
+                            let count = self.node_picker.matches(&self.graph).len();
+                            self.node_picker.move_selection(1, count);
+                        }
+                    }
+                    Some(Action::DeleteChar) => {
                         if let Some(CurrentlyEditing::Edge(mode)) = &self.currently_editing {
                             match mode {
                                 EdgeEditorMode::Label => {
-                                    self.label.pop();
+                                    self.label.backspace();
+                                }
+                                EdgeEditorMode::InOuts(_) if !self.node_picker.query.is_empty() => {
+                                    self.node_picker.pop();
                                 }
                                 EdgeEditorMode::InOuts(InOut::From) => {
                                     self.in_outs[0] = 0;
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::Label)
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::Label);
                                 }
                                 EdgeEditorMode::InOuts(InOut::To) => {
                                     self.in_outs[1] = 0;
-                                    self.currently_editing = Some(
-                                        CurrentlyEditing::Edge(EdgeEditorMode::InOuts(InOut::From))
-                                    );
+                                    self.set_edge_mode(EdgeEditorMode::InOuts(InOut::From));
                                 }
                             }
                         }
                     }
-                    KeyCode::Esc => {
-                        self.currently_editing = None;
+                    Some(Action::Cancel) => {
+                        self.close_edge_editor();
+                    }
+                    Some(action) => {
+                        self.edit_active_input(action);
+                    }
+                    None => {
+                        if let Some(value) = typed_char(&key) {
+                            self.insert_text(&value.to_string());
+                        }
+                    }
+                }
+            }
+            CurrentScreen::SaveAs => {
+                match action {
+                    Some(Action::Confirm) => {
+                        self.confirm_save_as();
+                    }
+                    Some(Action::Cancel) => {
+                        self.exit_after_save = false;
+                        self.current_screen = self.save_return;
+                    }
+                    Some(action) => {
+                        self.edit_active_input(action);
+                    }
+                    None => {
+                        if let Some(value) = typed_char(&key) {
+                            self.save_path_input.insert(value);
+                        }
+                    }
+                }
+            }
+            CurrentScreen::Palette => {
+                match action {
+                    Some(Action::Confirm) => {
+                        let selected = self.palette_matches().get(self.palette.index).copied();
+                        self.current_screen = self.palette_return;
+                        if let Some(action) = selected {
+                            self.run_action(action);
+                        }
+                    }
+                    Some(Action::Cancel) => {
+                        self.current_screen = self.palette_return;
+                    }
+                    Some(Action::Up) => {
+                        let count = self.palette_matches().len();
+                        self.palette.move_selection(-1, count);
+                    }
+                    Some(Action::Down) => {
+                        let count = self.palette_matches().len();
+                        self.palette.move_selection(1, count);
+                    }
+                    Some(Action::DeleteChar) => {
+                        self.palette.pop();
+                    }
+                    Some(_) => {}
+                    None => {
+                        if let Some(value) = typed_char(&key) {
+                            self.palette.push(value);
+                        }
+                    }
+                }
+            }
+            CurrentScreen::CommandLine => {
+                match action {
+                    Some(Action::Confirm) => {
+                        self.run_command_line();
+                    }
+                    Some(Action::Cancel) => {
+                        self.current_screen = self.command_return;
+                    }
+                    Some(Action::NextField) => {
+                        let (line, candidates) = command::complete(
+                            self.command_input.as_str(),
+                            &self.graph,
+                            &self.node_catalog
+                        );
+                        if candidates.len() > 1 {
+                            self.status = Some(candidates.join("  "));
+                        }
+                        self.command_input.set(line);
+                    }
+                    Some(Action::DeleteChar) if self.command_input.is_empty() => {
+                        // Backspace on an empty line leaves, as in vi.
+                        self.current_screen = self.command_return;
+                    }
+                    Some(action) => {
+                        self.edit_active_input(action);
+                    }
+                    None => {
+                        if let Some(value) = typed_char(&key) {
+                            self.command_input.insert(value);
+                        }
+                    }
+                }
+            }
+            _ => {
+                if let Some(action) = action {
+                    self.run_action(action);
+                }
+            }
+        }
+        // A move session ends when move mode is switched off or the canvas is left, so the
+        // next nudges undo on their own.
+        if self.current_screen != CurrentScreen::Canvas || !self.move_tool.active {
+            self.history.end_merge();
+        }
+    }
+
+    /// The text field keys are typed into on the current screen, if any.
+    fn active_input(&mut self) -> Option<&mut TextInput> {
+        match (self.current_screen, &self.currently_editing) {
+            (CurrentScreen::NodeEditor, Some(CurrentlyEditing::Node(NodeEditorMode::Label))) |
+            (CurrentScreen::EdgeEditor, Some(CurrentlyEditing::Edge(EdgeEditorMode::Label))) => {
+                Some(&mut self.label)
+            }
+            (CurrentScreen::NodeEditor, Some(CurrentlyEditing::Node(NodeEditorMode::Param))) => {
+                self.node_edit.as_mut().map(|edit| &mut edit.buffer)
+            }
+            (CurrentScreen::SaveAs, _) => Some(&mut self.save_path_input),
+            (CurrentScreen::CommandLine, _) => Some(&mut self.command_input),
+            _ => None,
+        }
+    }
+
+    /// Move the cursor or delete in the active text field; other actions are ignored.
+    fn edit_active_input(&mut self, action: Action) {
+        if let Some(input) = self.active_input() {
+            input.apply(action);
+        }
+    }
+
+    /// Insert typed or pasted text where the keyboard focus is: the active text field, or
+    /// the query of the palette or of the edge editor's node search.
+    pub fn insert_text(&mut self, text: &str) {
+        if let Some(input) = self.active_input() {
+            input.insert_str(text);
+            return;
+        }
+        let chars = text.chars().filter(|c| !c.is_control());
+        if self.current_screen == CurrentScreen::Palette {
+            chars.for_each(|c| self.palette.push(c));
+        } else if
+            self.current_screen == CurrentScreen::EdgeEditor &&
+            matches!(
+                self.currently_editing,
+                Some(CurrentlyEditing::Edge(EdgeEditorMode::InOuts(_)))
+            )
+        {
+            chars.for_each(|c| self.node_picker.push(c));
+            self.edge_error = None;
+        }
+    }
+
+    /// Perform `action` on the current screen, as its key would. Text-entry screens only
+    /// react to keys, so nothing happens there.
+    pub fn run_action(&mut self, action: Action) {
+        match action {
+            Action::OpenPalette => {
+                self.open_palette();
+                return;
+            }
+            Action::CommandLine => {
+                self.command_input.clear();
+                self.command_return = self.current_screen;
+                self.current_screen = CurrentScreen::CommandLine;
+                return;
+            }
+            _ => {}
+        }
+        match self.current_screen {
+            CurrentScreen::Main => {
+                match action {
+                    Action::OpenGraph => {
+                        self.current_screen = CurrentScreen::Graph;
+                    }
+                    Action::Save => {
+                        self.save();
+                    }
+                    Action::SaveAs => {
+                        self.open_save_as(false);
+                    }
+                    Action::Quit => {
+                        self.current_screen = CurrentScreen::Exiting;
+                    }
+                    _ => {}
+                }
+            }
+            CurrentScreen::Graph => {
+                match action {
+                    Action::EditGraph => {
+                        self.current_screen = CurrentScreen::GraphEditor;
+                    }
+                    Action::OpenCanvas => {
+                        self.open_canvas();
+                    }
+                    Action::Validate => {
+                        self.open_diagnostics();
+                    }
+                    Action::Save => {
+                        self.save();
+                    }
+                    Action::SaveAs => {
+                        self.open_save_as(false);
+                    }
+                    Action::Back => {
+                        self.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                }
+            }
+            CurrentScreen::GraphEditor => {
+                match action {
+                    Action::AddNode => {
+                        self.start_node_editor();
+                    }
+                    Action::Up => {
+                        self.move_cursor(-1);
+                    }
+                    Action::Down => {
+                        self.move_cursor(1);
+                    }
+                    Action::Confirm => {
+                        self.start_editing_selected_node();
+                    }
+                    Action::Delete => {
+                        self.delete_selected();
+                    }
+                    Action::OpenCanvas => {
+                        self.open_canvas();
+                    }
+                    Action::Validate => {
+                        self.open_diagnostics();
+                    }
+                    Action::Undo => {
+                        self.undo();
+                    }
+                    Action::Redo => {
+                        self.redo();
+                    }
+                    Action::Save => {
+                        self.save();
+                    }
+                    Action::SaveAs => {
+                        self.open_save_as(false);
+                    }
+                    Action::AddEdge => {
                         self.label.clear();
                         self.in_outs = [0, 0];
-                        self.current_screen = CurrentScreen::GraphEditor;
+                        self.node_picker.clear();
+                        self.edge_error = None;
+                        self.currently_editing = Some(
+                            CurrentlyEditing::Edge(EdgeEditorMode::Label)
+                        );
+                        self.current_screen = CurrentScreen::EdgeEditor;
                     }
-                    KeyCode::Char(value) => {
-                        if let Some(CurrentlyEditing::Edge(mode)) = &self.currently_editing {
-                            match mode {
-                                EdgeEditorMode::Label => {
-                                    self.label.push(value);
-                                }
-                                EdgeEditorMode::InOuts(InOut::From) => {
-                                    self.in_outs[0] = value.to_digit(10).unwrap_or(0) as u64;
-                                }
-                                EdgeEditorMode::InOuts(InOut::To) => {
-                                    self.in_outs[1] = value.to_digit(10).unwrap_or(0) as u64;
-                                }
-                            }
+                    Action::Back => {
+                        self.current_screen = CurrentScreen::Graph;
+                    }
+                    _ => {}
+                }
+            }
+            CurrentScreen::Canvas => {
+                match action {
+                    Action::PanLeft => self.canvas_direction(-1.0, 0.0),
+                    Action::PanRight => self.canvas_direction(1.0, 0.0),
+                    Action::PanUp => self.canvas_direction(0.0, -1.0),
+                    Action::PanDown => self.canvas_direction(0.0, 1.0),
+                    Action::SelectNext => self.cycle_canvas_selection(1),
+                    Action::SelectPrev => self.cycle_canvas_selection(-1),
+                    Action::ToggleMove => {
+                        self.move_tool.active = !self.move_tool.active;
+                        if self.move_tool.active && self.canvas_selected.is_none() {
+                            self.cycle_canvas_selection(1);
                         }
                     }
+                    Action::ToggleSnap => {
+                        self.move_tool.snap = !self.move_tool.snap;
+                    }
+                    Action::LayoutMissing => self.auto_layout(false),
+                    Action::LayoutAll => self.auto_layout(true),
+                    Action::Relax => self.start_force_layout(),
+                    // Settled above, like every other key.
+                    Action::StopLayout => {}
+                    Action::GrowStep => self.move_tool.grow_step(),
+                    Action::ShrinkStep => self.move_tool.shrink_step(),
+                    Action::Undo => self.undo(),
+                    Action::Redo => self.redo(),
+                    Action::Save => {
+                        self.save();
+                    }
+                    Action::SaveAs => self.open_save_as(false),
+                    Action::ZoomIn => self.viewport.zoom_in(),
+                    Action::ZoomOut => self.viewport.zoom_out(),
+                    Action::Fit => {
+                        self.fit_viewport();
+                    }
+                    Action::Back => {
+                        self.current_screen = self.canvas_return;
+                    }
+                    _ => {}
+                }
+            }
+            CurrentScreen::Diagnostics => {
+                match action {
+                    Action::Up => {
+                        self.diagnostics_index = self.diagnostics_index.saturating_sub(1);
+                    }
+                    Action::Down => {
+                        let last = self.diagnostics.len().saturating_sub(1);
+                        self.diagnostics_index = (self.diagnostics_index + 1).min(last);
+                    }
+                    Action::Confirm => {
+                        self.jump_to_diagnostic();
+                    }
+                    Action::Rerun => {
+                        self.run_validation();
+                    }
+                    Action::WriteAnyway if self.pending_save.is_some() => {
+                        self.force_pending_save();
+                    }
+                    Action::Back => {
+                        self.pending_save = None;
+                        self.exit_after_save = false;
+                        self.current_screen = self.diagnostics_return;
+                    }
                     _ => {}
                 }
             }
             CurrentScreen::Exiting => {
-                match key.code {
-                    KeyCode::Char('y') => {
+                match action {
+                    Action::ExitNow => {
                         self.exit = true;
                     }
-                    KeyCode::Char('n') | KeyCode::Char('q') => {
+                    Action::SaveAndExit if self.is_dirty() => {
+                        if self.file_path.is_some() {
+                            self.exit_after_save = true;
+                            if self.save() {
+                                self.exit = true;
+                            } else if self.pending_save.is_none() {
+                                self.exit_after_save = false;
+                            }
+                        } else {
+                            self.open_save_as(true);
+                        }
+                    }
+                    Action::Back => {
                         self.current_screen = CurrentScreen::Main;
                     }
                     _ => {}
                 }
             }
+            CurrentScreen::NodeEditor |
+            CurrentScreen::EdgeEditor |
+            CurrentScreen::SaveAs |
+            CurrentScreen::Palette |
+            CurrentScreen::CommandLine => {}
+        }
+    }
+
+    pub fn add_node(&mut self) {
+        let id = self.graph.get_new_node_id();
+        let type_name = self.current_type_name().unwrap_or("unknown");
+        let label = self.label.as_str().to_string();
+        let instance = NodeInstance::new(id, type_name.to_string(), label);
+        let mut tx = Transaction::new("add node");
+        tx.add_node(&mut self.graph, instance);
+        self.history.record(tx);
+    }
+
+    /// Whether the graph changed since it was loaded or last saved.
+    pub fn is_dirty(&self) -> bool {
+        self.history.state() != self.saved_state
+    }
+
+    /// Save to `file_path`, or open the Save As dialog if there is none yet.
+    pub fn save(&mut self) -> bool {
+        let Some(path) = self.file_path.clone() else {
+            self.open_save_as(false);
+            return false;
+        };
+        self.save_to(&path)
+    }
+
+    /// Validate, then write to `path`. Errors block the write and open the diagnostics
+    /// panel (where `W` writes anyway); warnings are only reported in the status line.
+    fn save_to(&mut self, path: &str) -> bool {
+        let diagnostics = validate(&self.graph, &self.node_catalog);
+        let errors = error_count(&diagnostics);
+        if errors > 0 {
+            self.show_diagnostics(diagnostics);
+            self.pending_save = Some(path.to_string());
+            self.status = Some(
+                format!("Save blocked: {} error(s). W writes anyway, Esc cancels", errors)
+            );
+            return false;
+        }
+        let warnings = diagnostics.len();
+        if !self.write_to(path) {
+            return false;
+        }
+        if warnings > 0 {
+            self.status = Some(format!("Saved {} with {} warning(s)", path, warnings));
+        }
+        true
+    }
+
+    fn write_to(&mut self, path: &str) -> bool {
+        match write_graph_to_path(path, self.file_format, &self.graph, &self.node_catalog) {
+            Ok(()) => {
+                self.file_path = Some(path.to_string());
+                self.saved_state = self.history.state();
+                self.status = Some(format!("Saved {}", path));
+                true
+            }
+            Err(error) => {
+                self.status = Some(format!("Save failed: {}", error));
+                false
+            }
+        }
+    }
+
+    /// Write the save that validation blocked, then carry on as the save would have.
+    fn force_pending_save(&mut self) {
+        let Some(path) = self.pending_save.take() else {
+            return;
+        };
+        if self.write_to(&path) {
+            self.current_screen = self.diagnostics_return;
+            if self.exit_after_save {
+                self.exit = true;
+            }
+        }
+    }
+
+    pub fn open_diagnostics(&mut self) {
+        self.run_validation();
+        self.pending_save = None;
+        let diagnostics = std::mem::take(&mut self.diagnostics);
+        self.show_diagnostics(diagnostics);
+    }
+
+    fn show_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        if self.current_screen != CurrentScreen::Diagnostics {
+            self.diagnostics_return = match self.current_screen {
+                CurrentScreen::SaveAs => self.save_return,
+                CurrentScreen::Exiting => CurrentScreen::Main,
+                screen => screen,
+            };
+        }
+        self.diagnostics = diagnostics;
+        self.diagnostics_index = 0;
+        self.current_screen = CurrentScreen::Diagnostics;
+    }
+
+    fn run_validation(&mut self) {
+        self.diagnostics = validate(&self.graph, &self.node_catalog);
+        let last = self.diagnostics.len().saturating_sub(1);
+        self.diagnostics_index = self.diagnostics_index.min(last);
+        let errors = error_count(&self.diagnostics);
+        let warnings = self.diagnostics.len() - errors;
+        self.status = Some(format!("{} error(s), {} warning(s)", errors, warnings));
+    }
+
+    /// Put the graph editor's cursor on the item the selected diagnostic refers to.
+    fn jump_to_diagnostic(&mut self) {
+        let Some(diagnostic) = self.diagnostics.get(self.diagnostics_index) else {
+            return;
+        };
+        let position = match diagnostic.item {
+            GraphItem::Node(id) => self.graph.nodes.iter().position(|n| n.id == id),
+            GraphItem::Edge(id) => {
+                self.graph.edges
+                    .iter()
+                    .position(|e| e.id == id)
+                    .map(|index| self.graph.nodes.len() + index)
+            }
+        };
+        match position {
+            Some(position) => {
+                self.cursor = position;
+                self.pending_save = None;
+                self.exit_after_save = false;
+                self.current_screen = CurrentScreen::GraphEditor;
+            }
+            None => {
+                self.status = Some("That item no longer exists".to_string());
+            }
+        }
+    }
+
+    fn open_save_as(&mut self, exit_after_save: bool) {
+        self.save_path_input.set(self.file_path.clone().unwrap_or_default());
+        self.save_return = self.current_screen;
+        self.exit_after_save = exit_after_save;
+        self.current_screen = CurrentScreen::SaveAs;
+    }
+
+    fn confirm_save_as(&mut self) {
+        let path = self.save_path_input.as_str().trim().to_string();
+        if path.is_empty() {
+            self.status = Some("Enter a file path".to_string());
+            return;
+        }
+        if self.save_to(&path) {
+            self.current_screen = self.save_return;
+            if self.exit_after_save {
+                self.exit = true;
+            }
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.status = match self.history.undo(&mut self.graph) {
+            Some(label) => Some(format!("Undid {}", label)),
+            None => Some("Nothing to undo".to_string()),
+        };
+        self.after_history_change();
+    }
+
+    pub fn redo(&mut self) {
+        self.status = match self.history.redo(&mut self.graph) {
+            Some(label) => Some(format!("Redid {}", label)),
+            None => Some("Nothing to redo".to_string()),
+        };
+        self.after_history_change();
+    }
+
+    fn after_history_change(&mut self) {
+        self.move_cursor(0);
+        if let Some(id) = self.canvas_selected && self.graph.get_node(id).is_none() {
+            self.canvas_selected = None;
+        }
+    }
+
+    fn start_node_editor(&mut self) {
+        self.label.clear();
+        self.node_edit = None;
+        self.editing_node = None;
+        self.node_type_index = 0;
+        if self.node_type_keys.is_empty() {
+            self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Label));
+        } else {
+            self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Type));
+        }
+        self.current_screen = CurrentScreen::NodeEditor;
+    }
+
+    /// Open the node editor pre-populated from the node under the cursor.
+    ///
+    /// The editor starts on the type step with the node's current type selected, so
+    /// Enter keeps it and Up/Down switches it; values carry over to the new type.
+    fn start_editing_selected_node(&mut self) {
+        let Some(GraphItem::Node(id)) = self.selected_item() else {
+            return;
+        };
+        let Some(node) = self.graph.get_node(id) else {
+            return;
+        };
+        self.editing_node = Some(node.id);
+        self.label.set(node.label.clone());
+        self.node_edit = self.node_catalog.nodes.types
+            .get(node.type_.as_str())
+            .map(|def| NodeEditState::with_values(node.type_.clone(), def, node.values.clone()));
+        match self.node_type_keys.iter().position(|key| key == &node.type_) {
+            Some(index) => {
+                self.node_type_index = index;
+                self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Type));
+            }
+            None => {
+                // Types outside the catalog cannot be re-selected; only the label is editable.
+                self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Label));
+            }
+        }
+        self.current_screen = CurrentScreen::NodeEditor;
+    }
+
+    fn close_node_editor(&mut self) {
+        self.label.clear();
+        self.currently_editing = None;
+        self.current_screen = CurrentScreen::GraphEditor;
+        self.node_edit = None;
+        self.editing_node = None;
+    }
+
+    pub fn selected_item(&self) -> Option<GraphItem> {
+        let nodes = self.graph.nodes.len();
+        if self.cursor < nodes {
+            return Some(GraphItem::Node(self.graph.nodes[self.cursor].id));
+        }
+        self.graph.edges.get(self.cursor - nodes).map(|edge| GraphItem::Edge(edge.id))
+    }
+
+    fn move_cursor(&mut self, delta: i32) {
+        let len = self.graph.nodes.len() + self.graph.edges.len();
+        if len == 0 {
+            self.cursor = 0;
+            return;
+        }
+        let next = (self.cursor as i32) + delta;
+        self.cursor = next.clamp(0, (len as i32) - 1) as usize;
+    }
+
+    fn delete_selected(&mut self) {
+        match self.selected_item() {
+            Some(GraphItem::Node(id)) => self.delete_node(id),
+            Some(GraphItem::Edge(id)) => self.delete_edge(id),
+            None => {}
         }
+        self.move_cursor(0);
     }
 
-    pub fn add_node(&mut self) {
-        let id = self.graph.get_new_node_id();
-        let type_name = self.current_type_name().unwrap_or("unknown");
-        let instance = NodeInstance::new(id, type_name.to_string(), self.label.clone());
-        self.graph.add_node(instance);
+    /// Remove a node together with every edge attached to it.
+    pub fn delete_node(&mut self, id: usize) {
+        let mut tx = Transaction::new("delete node");
+        remove_node_cascading(&mut self.graph, &mut tx, id);
+        self.history.record(tx);
     }
 
-    fn start_node_editor(&mut self) {
-        self.label.clear();
-        self.node_edit = None;
-        self.node_type_index = 0;
-        if self.node_type_keys.is_empty() {
-            self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Label));
-        } else {
-            self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Type));
+    pub fn delete_edge(&mut self, id: u64) {
+        let mut tx = Transaction::new("delete edge");
+        disconnect(&mut self.graph, &mut tx, id);
+        self.history.record(tx);
+    }
+
+    /// Commit the param being typed and move to a neighbouring one.
+    fn jump_param(&mut self, delta: i32) {
+        if !self.commit_current_param() {
+            return;
+        }
+        if let Some(edit) = &mut self.node_edit {
+            let target = (edit.index as i32) + delta;
+            if target >= 0 {
+                edit.set_index(target as usize);
+            }
+        }
+    }
+
+    /// Commit the param being typed and move to the next one, wrapping to the first.
+    fn cycle_param(&mut self) {
+        if !self.commit_current_param() {
+            return;
+        }
+        if let Some(edit) = &mut self.node_edit && edit.has_params() {
+            edit.set_index((edit.index + 1) % edit.param_count());
         }
-        self.current_screen = CurrentScreen::NodeEditor;
     }
 
     fn advance_node_editor(&mut self) {
         match &self.currently_editing {
             Some(CurrentlyEditing::Node(NodeEditorMode::Label)) => {
-                if let Some(edit) = &self.node_edit {
-                    if edit.has_params() {
+                if let Some(edit) = &self.node_edit && edit.has_params() {
+                    self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Param));
+                    return;
+                }
+                self.open_node_review();
+            }
+            Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => {
+                if self.commit_current_param() && let Some(edit) = &mut self.node_edit {
+                    if edit.from_review {
+                        // Back to the summary, still on the param just edited.
+                        edit.from_review = false;
+                        edit.load_buffer();
                         self.currently_editing = Some(
-                            CurrentlyEditing::Node(NodeEditorMode::Param)
+                            CurrentlyEditing::Node(NodeEditorMode::Review)
                         );
                         return;
                     }
-                }
-                self.finalize_node_edit();
-            }
-            Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => {
-                if self.commit_current_param() {
-                    if let Some(edit) = &mut self.node_edit {
-                        edit.advance();
-                        if edit.is_done() {
-                            self.finalize_node_edit();
-                        }
+                    edit.advance();
+                    if edit.is_done() {
+                        self.open_node_review();
+                    } else {
+                        edit.load_buffer();
                     }
                 }
             }
@@ -363,6 +1047,79 @@ impl App {
         }
     }
 
+    /// Show the summary of every param, with the first editable one highlighted.
+    fn open_node_review(&mut self) {
+        if let Some(edit) = &mut self.node_edit {
+            edit.set_index(0);
+            edit.from_review = false;
+        }
+        self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Review));
+    }
+
+    fn move_review_selection(&mut self, delta: i32) {
+        if let Some(edit) = &mut self.node_edit && edit.has_params() {
+            let last = (edit.param_count() as i32) - 1;
+            edit.set_index(((edit.index as i32) + delta).clamp(0, last) as usize);
+        }
+    }
+
+    /// Leave the review to edit the highlighted param (the label for param-less types);
+    /// Enter then comes straight back to the review.
+    fn edit_reviewed_param(&mut self) {
+        let mode = match &mut self.node_edit {
+            Some(edit) if edit.has_params() => {
+                edit.from_review = true;
+                NodeEditorMode::Param
+            }
+            _ => NodeEditorMode::Label,
+        };
+        self.currently_editing = Some(CurrentlyEditing::Node(mode));
+    }
+
+    /// Every param of the node's type with the value the node would be written with:
+    /// the editable ones first, in editor order (so row `i` is `param_keys[i]`), then
+    /// the ones the editor maintains itself (`name`, `in`, `out`, ...).
+    pub fn param_review(&self) -> Vec<(String, ParamStatus)> {
+        let Some(edit) = &self.node_edit else {
+            return Vec::new();
+        };
+        let node = self.pending_node();
+        let mut keys: Vec<&String> = edit.params.keys().collect();
+        keys.sort_by_key(|key| (!is_editable_param(key), *key));
+        keys.into_iter()
+            .map(|key| {
+                let status = match node.values.get(key) {
+                    Some(value) => ParamStatus::Set(value_to_input(value)),
+                    None =>
+                        match self.graph.derived_value(&node, &edit.params[key]) {
+                            Some(value) => ParamStatus::Derived(value_to_input(&value)),
+                            None => ParamStatus::Unset,
+                        }
+                };
+                (key.clone(), status)
+            })
+            .collect()
+    }
+
+    /// The node as `finalize_node_edit` would write it.
+    pub fn pending_node(&self) -> NodeInstance {
+        let existing = self.editing_node.and_then(|id| self.graph.get_node(id));
+        let mut node = match existing {
+            Some(node) => node.clone(),
+            None => {
+                let id = self.graph.get_new_node_id();
+                let type_name = self.current_type_name().unwrap_or("unknown");
+                NodeInstance::new(id, type_name.to_string(), String::new())
+            }
+        };
+        node.label = self.label.as_str().to_string();
+        if let Some(edit) = &self.node_edit {
+            node.type_ = edit.type_name.clone();
+            node.values = edit.values.clone();
+        }
+        node
+    }
+
     fn commit_current_param(&mut self) -> bool {
         let Some(edit) = &mut self.node_edit else {
             return true;
@@ -370,17 +1127,17 @@ impl App {
         let Some(param_def) = edit.current_def() else {
             return true;
         };
-        if edit.buffer.trim().is_empty() {
+        if edit.buffer.as_str().trim().is_empty() {
+            // An emptied (possibly pre-filled) input unsets the param.
+            edit.clear_value();
             edit.error = None;
             return true;
         }
-        let result = parse_param_value(&edit.buffer, param_def);
+        let result = parse_param_value(edit.buffer.as_str(), param_def);
         match result {
             Ok(value) => {
-                if edit.current_key() == Some("name") {
-                    if let toml::Value::String(name) = &value {
-                        self.label = name.clone();
-                    }
+                if edit.current_key() == Some("name") && let toml::Value::String(name) = &value {
+                    self.label.set(name.clone());
                 }
                 edit.set_value(value);
                 edit.buffer.clear();
@@ -394,21 +1151,23 @@ impl App {
         }
     }
 
+    /// Write `pending_node()`, the node the review showed.
     fn finalize_node_edit(&mut self) {
-        let id = self.graph.get_new_node_id();
-        let instance = if let Some(edit) = &self.node_edit {
-            let mut instance = NodeInstance::new(id, edit.type_name.clone(), self.label.clone());
-            instance.values = edit.values.clone();
-            instance
-        } else {
-            let type_name = self.current_type_name().unwrap_or("unknown");
-            NodeInstance::new(id, type_name.to_string(), self.label.clone())
+        let node = self.pending_node();
+        let tx = match self.editing_node {
+            Some(id) => {
+                let mut tx = Transaction::new("edit node");
+                tx.update_node(&mut self.graph, id, |existing| *existing = node);
+                tx
+            }
+            None => {
+                let mut tx = Transaction::new("add node");
+                tx.add_node(&mut self.graph, node);
+                tx
+            }
         };
-        self.graph.add_node(instance);
-        self.label.clear();
-        self.current_screen = CurrentScreen::GraphEditor;
-        self.currently_editing = None;
-        self.node_edit = None;
+        self.history.record(tx);
+        self.close_node_editor();
     }
 
     pub fn current_type_name(&self) -> Option<&str> {
@@ -431,26 +1190,266 @@ impl App {
     }
 
     fn select_current_type(&mut self) {
-        if let Some(type_name) = self.current_type_name() {
-            if let Some(def) = self.node_catalog.nodes.types.get(type_name) {
-                self.node_edit = Some(NodeEditState::new(type_name.to_string(), def));
-            }
+        if
+            let Some(type_name) = self.current_type_name() &&
+            let Some(def) = self.node_catalog.nodes.types.get(type_name)
+        {
+            // When editing, keep whatever values still make sense for the chosen type.
+            let values = match &self.node_edit {
+                Some(edit) if self.editing_node.is_some() && edit.type_name == type_name => {
+                    edit.values.clone()
+                }
+                Some(edit) if self.editing_node.is_some() => {
+                    edit.values
+                        .iter()
+                        .filter(|(key, _)| def.params.contains_key(key.as_str()))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect()
+                }
+                _ => HashMap::new(),
+            };
+            self.node_edit = Some(
+                NodeEditState::with_values(type_name.to_string(), def, values)
+            );
         }
         self.currently_editing = Some(CurrentlyEditing::Node(NodeEditorMode::Label));
     }
 
+    fn open_canvas(&mut self) {
+        self.canvas_return = self.current_screen;
+        if let Some(GraphItem::Node(id)) = self.selected_item() {
+            self.canvas_selected = Some(id);
+        }
+        if let Some(id) = self.canvas_selected && self.graph.get_node(id).is_none() {
+            self.canvas_selected = None;
+        }
+        self.fit_viewport();
+        self.current_screen = CurrentScreen::Canvas;
+    }
+
+    /// Arrow keys on the canvas: move the selected node in move mode, otherwise pan.
+    fn canvas_direction(&mut self, dx: f64, dy: f64) {
+        if self.move_tool.active && let Some(id) = self.canvas_selected {
+            self.nudge_node(id, dx, dy);
+        } else {
+            self.viewport.pan(dx, dy);
+        }
+    }
+
+    fn cycle_canvas_selection(&mut self, delta: i32) {
+        let len = self.graph.nodes.len() as i32;
+        if len == 0 {
+            self.canvas_selected = None;
+            return;
+        }
+        let current = self.canvas_selected.and_then(|id| {
+            self.graph.nodes.iter().position(|node| node.id == id)
+        });
+        let next = match current {
+            Some(index) => ((index as i32) + delta).rem_euclid(len),
+            None if delta < 0 => len - 1,
+            None => 0,
+        };
+        self.canvas_selected = Some(self.graph.nodes[next as usize].id);
+    }
+
+    /// Move a node by one step, writing the result into its position table. Nodes that
+    /// have no position yet start from the middle of the current view.
+    pub fn nudge_node(&mut self, id: usize, dx: f64, dy: f64) {
+        let center = (self.viewport.center_x, self.viewport.center_y);
+        let mut tx = Transaction::new(format!("move node {}", id));
+        tx.update_node(&mut self.graph, id, |node| {
+            let coords = self.node_catalog.node_coords(node).unwrap_or(center);
+            let (x, y) = self.move_tool.nudge(coords, dx, dy);
+            self.node_catalog.set_node_coords(node, x, y);
+        });
+        self.history.record_merging(tx);
+    }
+
+    /// Lay out nodes without coordinates (or every node, with `all`) as one undo step.
+    pub fn auto_layout(&mut self, all: bool) {
+        let mut tx = Transaction::new(if all { "layout all" } else { "layout" });
+        let count = apply_layout(&mut self.graph, &mut tx, &self.node_catalog, all);
+        self.history.record(tx);
+        self.status = Some(match count {
+            0 => "Nothing to lay out".to_string(),
+            count => format!("Placed {} node(s)", count),
+        });
+        self.fit_viewport();
+    }
+
+    /// Execute the `:` line as one undo step. Errors keep the line open for fixing.
+    fn run_command_line(&mut self) {
+        let line = self.command_input.as_str().trim().to_string();
+        if line.is_empty() {
+            self.current_screen = self.command_return;
+            return;
+        }
+        let mut tx = Transaction::new(format!(":{}", line));
+        match command::execute(&line, &mut self.graph, &mut tx, &self.node_catalog) {
+            Ok(Outcome::Edited(message)) => {
+                self.history.record(tx);
+                self.current_screen = self.command_return;
+                self.move_cursor(0);
+                self.status = Some(message);
+            }
+            Ok(Outcome::Write(path)) => {
+                self.current_screen = self.command_return;
+                match path {
+                    Some(path) => self.save_to(&path),
+                    None => self.save(),
+                };
+            }
+            Err(error) => {
+                self.status = Some(error);
+            }
+        }
+    }
+
+    /// Palette entries for the screen it was opened from, leaving out actions that would
+    /// do nothing right now.
+    pub fn palette_matches(&self) -> Vec<Action> {
+        let context = KeyContext::for_screen(self.palette_return);
+        self.palette.matches(&self.keymap, context, |action| self.action_available(action))
+    }
+
+    /// Whether running `action` would do anything in the current state.
+    fn action_available(&self, action: Action) -> bool {
+        match action {
+            Action::WriteAnyway => self.pending_save.is_some(),
+            Action::SaveAndExit => self.is_dirty(),
+            Action::StopLayout => self.force_layout.is_some(),
+            _ => true,
+        }
+    }
+
+    fn open_palette(&mut self) {
+        self.palette.clear();
+        self.palette_return = self.current_screen;
+        self.current_screen = CurrentScreen::Palette;
+    }
+
+    /// Start animating a seeded force-directed layout of every node with `coords`.
+    pub fn start_force_layout(&mut self) {
+        let layout = ForceLayout::new(&self.graph, &self.node_catalog, DEFAULT_SEED, true);
+        if layout.is_done() {
+            self.status = Some("Nothing to lay out".to_string());
+            return;
+        }
+        self.force_before = self.graph.nodes.clone();
+        self.force_layout = Some(layout);
+        self.show_force_positions();
+        self.fit_viewport();
+        self.status = Some(match self.keymap.primary(KeyContext::Canvas, Action::StopLayout) {
+            Some(key) => format!("Relaxing layout… <{}> to stop", key),
+            None => "Relaxing layout… any key stops it".to_string(),
+        });
+    }
+
+    /// Write the running force layout's current positions into the graph.
+    fn show_force_positions(&mut self) {
+        let Some(layout) = &self.force_layout else {
+            return;
+        };
+        let positions = layout.positions();
+        for node in &mut self.graph.nodes {
+            if let Some(&(x, y)) = positions.get(&node.id) {
+                self.node_catalog.set_node_coords(node, x, y);
+            }
+        }
+    }
+
+    /// Stop the animation and record everything it moved as a single transaction.
+    fn finish_force_layout(&mut self) {
+        if self.force_layout.take().is_none() {
+            return;
+        }
+        let mut tx = Transaction::new("force layout");
+        let mut count = 0;
+        for before in std::mem::take(&mut self.force_before) {
+            let id = before.id;
+            let Some(index) = self.graph.nodes.iter().position(|node| node.id == id) else {
+                continue;
+            };
+            let after = std::mem::replace(&mut self.graph.nodes[index], before);
+            if tx.update_node(&mut self.graph, id, |node| *node = after) {
+                count += 1;
+            }
+        }
+        self.history.record(tx);
+        self.status = Some(format!("Relaxed {} node(s)", count));
+        self.fit_viewport();
+    }
+
+    fn fit_viewport(&mut self) {
+        let points: Vec<(f64, f64)> = self.graph.nodes
+            .iter()
+            .filter_map(|node| self.node_catalog.node_coords(node))
+            .collect();
+        self.viewport.fit(&points);
+    }
+
+    fn set_edge_mode(&mut self, mode: EdgeEditorMode) {
+        self.node_picker.clear();
+        self.edge_error = None;
+        self.currently_editing = Some(CurrentlyEditing::Edge(mode));
+    }
+
+    /// Resolve the picker for endpoint `slot` (0 = from, 1 = to).
+    ///
+    /// An empty query keeps an endpoint picked earlier (e.g. when tabbing back).
+    fn pick_endpoint(&mut self, slot: usize) -> bool {
+        let picked = self.graph.get_node(self.in_outs[slot] as usize).is_some();
+        if self.node_picker.query.is_empty() && picked {
+            return true;
+        }
+        match self.node_picker.selected(&self.graph) {
+            Some(id) => {
+                self.in_outs[slot] = id as u64;
+                true
+            }
+            None => {
+                self.edge_error = Some(format!("no node matches '{}'", self.node_picker.query));
+                false
+            }
+        }
+    }
+
+    fn finalize_edge_edit(&mut self) {
+        let [from, to] = self.in_outs;
+        let missing = [from, to]
+            .into_iter()
+            .find(|id| self.graph.get_node(*id as usize).is_none());
+        if let Some(id) = missing {
+            self.edge_error = Some(format!("node {} does not exist", id));
+            return;
+        }
+        self.add_edge();
+        self.close_edge_editor();
+    }
+
+    fn close_edge_editor(&mut self) {
+        self.label.clear();
+        self.in_outs = [0, 0];
+        self.node_picker.clear();
+        self.edge_error = None;
+        self.currently_editing = None;
+        self.current_screen = CurrentScreen::GraphEditor;
+    }
+
     pub fn add_edge(&mut self) {
         let from = self.in_outs[0];
         let to = self.in_outs[1];
-        let id = (self.graph.edges.len() as u64) + 1;
-        let label = self.label.clone();
-        self.graph.add_edge(crate::edge::Edge {
-            id,
-            from,
-            to,
-            label: label.clone(),
-        });
-        self.apply_edge_to_node_io(from, to, &label);
+        let id = self.graph.get_new_edge_id();
+        let label = self.label.as_str().to_string();
+        let mut tx = Transaction::new("add edge");
+        connect(&mut self.graph, &mut tx, Edge { id, from, to, label });
+        self.history.record(tx);
+    }
+
+    /// Recompute the recycle loops if the graph changed since the last frame.
+    pub fn refresh_loops(&mut self) {
+        self.loops.refresh(&self.graph, self.history.state());
     }
 
     pub fn on_tick(&mut self) {
@@ -495,41 +1494,120 @@ fn apply_list_value(node: &mut NodeInstance, key: &str, value: &str) {
     }
 }
 
-fn update_node_io(nodes: &mut [NodeInstance], node_id: u64, key: &str, stream: &str) {
-    let Ok(node_id) = usize::try_from(node_id) else {
-        return;
-    };
-    if let Some(node) = nodes.iter_mut().find(|node| node.id == node_id) {
-        apply_list_value(node, key, stream);
+fn retract_list_value(node: &mut NodeInstance, key: &str, value: &str) {
+    match node.values.get_mut(key) {
+        Some(toml::Value::Array(items)) => {
+            items.retain(|item| item.as_str() != Some(value));
+            if items.is_empty() {
+                node.values.remove(key);
+            }
+        }
+        Some(toml::Value::String(existing)) if existing == value => {
+            node.values.remove(key);
+        }
+        _ => {}
     }
 }
 
-impl App {
-    fn apply_edge_to_node_io(&mut self, from: u64, to: u64, label: &str) {
-        update_node_io(&mut self.graph.nodes, from, "out", label);
-        update_node_io(&mut self.graph.nodes, to, "in", label);
+/// Add `edge` and record its stream name in the endpoints' `out`/`in` values.
+pub fn connect(graph: &mut Graph, tx: &mut Transaction, edge: Edge) {
+    let (from, to, label) = (edge.from, edge.to, edge.label.clone());
+    tx.add_edge(graph, edge);
+    apply_edge_to_node_io(graph, tx, from, to, &label);
+}
+
+/// Remove an edge and retract its stream name from the endpoints' `in`/`out` values.
+pub fn disconnect(graph: &mut Graph, tx: &mut Transaction, id: u64) -> Option<Edge> {
+    let edge = tx.remove_edge(graph, id)?;
+    retract_edge_from_node_io(graph, tx, edge.from, edge.to, &edge.label);
+    Some(edge)
+}
+
+/// Remove a node together with every edge attached to it.
+pub fn remove_node_cascading(
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    id: usize
+) -> Option<NodeInstance> {
+    for edge_id in graph.attached_edge_ids(id) {
+        disconnect(graph, tx, edge_id);
+    }
+    tx.remove_node(graph, id)
+}
+
+fn apply_edge_to_node_io(graph: &mut Graph, tx: &mut Transaction, from: u64, to: u64, label: &str) {
+    if let Ok(from) = usize::try_from(from) {
+        tx.update_node(graph, from, |node| apply_list_value(node, "out", label));
+    }
+    if let Ok(to) = usize::try_from(to) {
+        tx.update_node(graph, to, |node| apply_list_value(node, "in", label));
+    }
+}
+
+/// Undo `apply_edge_to_node_io` for a removed edge, unless another remaining edge on
+/// the same side of that node still carries the stream.
+fn retract_edge_from_node_io(
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    from: u64,
+    to: u64,
+    label: &str
+) {
+    let still_out = graph.edges.iter().any(|e| e.from == from && e.label == label);
+    let still_in = graph.edges.iter().any(|e| e.to == to && e.label == label);
+    if !still_out && let Ok(from) = usize::try_from(from) {
+        tx.update_node(graph, from, |node| retract_list_value(node, "out", label));
+    }
+    if !still_in && let Ok(to) = usize::try_from(to) {
+        tx.update_node(graph, to, |node| retract_list_value(node, "in", label));
+    }
+}
+
+/// The character a key types into a text field; Ctrl and Alt chords type nothing.
+fn typed_char(key: &KeyEvent) -> Option<char> {
+    let chord = key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+    match key.code {
+        KeyCode::Char(c) if !chord => Some(c),
+        _ => None,
     }
 }
 
+/// Params typed by hand: the label (`name`) and stream lists (`in`/`out`) are maintained
+/// by the editor itself.
+pub fn is_editable_param(key: &str) -> bool {
+    !matches!(key, "name" | "in" | "out" | "ins" | "outs")
+}
+
 #[derive(Debug)]
 pub struct NodeEditState {
     pub type_name: String,
     pub param_keys: Vec<String>,
     pub index: usize,
-    pub buffer: String,
+    pub buffer: TextInput,
     pub values: HashMap<String, toml::Value>,
     pub error: Option<String>,
+    /// The param was opened from the review, so committing it returns there.
+    pub from_review: bool,
     params: HashMap<String, ParamDef>,
 }
 
 impl NodeEditState {
+    /// Edit state for a node that already carries `values`; the first param is pre-filled.
+    pub fn with_values(
+        type_name: String,
+        def: &NodeTypeDef,
+        values: HashMap<String, toml::Value>
+    ) -> Self {
+        let mut state = Self::new(type_name, def);
+        state.values = values;
+        state.load_buffer();
+        state
+    }
+
     pub fn new(type_name: String, def: &NodeTypeDef) -> Self {
         let mut param_keys: Vec<String> = def.params
             .keys()
-            .filter(|key| {
-                let key = key.as_str();
-                key != "name" && key != "in" && key != "out" && key != "ins" && key != "outs"
-            })
+            .filter(|key| is_editable_param(key))
             .cloned()
             .collect();
         param_keys.sort();
@@ -537,9 +1615,10 @@ impl NodeEditState {
             type_name,
             param_keys,
             index: 0,
-            buffer: String::new(),
+            buffer: TextInput::default(),
             values: HashMap::new(),
             error: None,
+            from_review: false,
             params: def.params.clone(),
         }
     }
@@ -552,6 +1631,10 @@ impl NodeEditState {
         self.param_keys.get(self.index).map(|key| key.as_str())
     }
 
+    pub fn param_count(&self) -> usize {
+        self.param_keys.len()
+    }
+
     pub fn current_def(&self) -> Option<&ParamDef> {
         let key = self.current_key()?;
         self.params.get(key)
@@ -563,6 +1646,32 @@ impl NodeEditState {
         }
     }
 
+    pub fn clear_value(&mut self) {
+        if let Some(key) = self.current_key() {
+            let key = key.to_string();
+            self.values.remove(&key);
+        }
+    }
+
+    /// Jump straight to the param at `index` and pre-fill the input with its value.
+    pub fn set_index(&mut self, index: usize) {
+        if index < self.param_keys.len() {
+            self.index = index;
+            self.load_buffer();
+        }
+    }
+
+    /// Replace the input buffer with the current param's value, if it has one.
+    pub fn load_buffer(&mut self) {
+        let value = self
+            .current_key()
+            .and_then(|key| self.values.get(key))
+            .map(value_to_input)
+            .unwrap_or_default();
+        self.buffer.set(value);
+        self.error = None;
+    }
+
     pub fn advance(&mut self) {
         if self.index < self.param_keys.len() {
             self.index += 1;
@@ -574,7 +1683,15 @@ impl NodeEditState {
     }
 }
 
-fn parse_param_value(raw: &str, def: &ParamDef) -> Result<toml::Value, String> {
+/// Text a user would type to reproduce `value` via `parse_param_value`.
+fn value_to_input(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => render_inline_value(other).unwrap_or_default(),
+    }
+}
+
+pub fn parse_param_value(raw: &str, def: &ParamDef) -> Result<toml::Value, String> {
     match def.kind {
         ParamType::String => Ok(toml::Value::String(raw.to_string())),
         ParamType::Float => {
@@ -733,7 +1850,18 @@ pub fn load_node_catalog_from_path(path: &str) -> Result<NodeTypeCatalog, String
     toml::from_str(&raw).map_err(|err| err.to_string())
 }
 
-fn load_node_catalog_default() -> NodeTypeCatalog {
+/// Read a units file as `format`, or as its extension suggests when `None`.
+pub fn load_graph_from_path(
+    path: &str,
+    format: Option<GraphFormat>,
+    catalog: &NodeTypeCatalog
+) -> Result<Graph, String> {
+    let raw = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let format = format.unwrap_or_else(|| GraphFormat::for_path(path));
+    Graph::from_format_str(&raw, format, catalog)
+}
+
+pub fn load_node_catalog_default() -> NodeTypeCatalog {
     let raw = include_str!("../templates/units.toml");
     toml::from_str(raw).unwrap_or_else(|_| NodeTypeCatalog {
         format: None,
@@ -749,24 +1877,343 @@ fn node_type_keys(catalog: &NodeTypeCatalog) -> Vec<String> {
 
 pub fn write_graph_to_path(
     path: &str,
+    format: Option<GraphFormat>,
     graph: &Graph,
     catalog: &NodeTypeCatalog
 ) -> Result<(), String> {
-    let string_result = graph.to_units_toml_string(catalog);
-    match string_result {
-        Ok(string) => {
-            let result = std::fs::write(&path, &string);
-            match result {
-                Ok(()) => {
-                    return Ok(());
-                }
-                Err(error) => {
-                    return Err(error.to_string());
-                }
-            }
+    let format = format.unwrap_or_else(|| GraphFormat::for_path(path));
+    let string = graph.to_format_string(format, catalog)?;
+    std::fs::write(path, &string).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.on_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn editing_a_node_prefills_and_writes_back_values() {
+        let mut app = App::new();
+        let mut node = NodeInstance::new(1, "cstr".to_string(), "lane1.t1".to_string());
+        node.values.insert("parameter_tau".to_string(), toml::Value::Float(1.0));
+        node.values.insert("in".to_string(), toml::Value::String("feed".to_string()));
+        app.graph.add_node(node);
+        app.current_screen = CurrentScreen::GraphEditor;
+
+        press(&mut app, KeyCode::Enter); // edit selected node
+        assert_eq!(app.editing_node, Some(1));
+        press(&mut app, KeyCode::Enter); // keep type
+        press(&mut app, KeyCode::Backspace);
+        type_text(&mut app, "2");
+        press(&mut app, KeyCode::Enter); // label -> params
+
+        // Jump down to parameter_tau, which is pre-filled with its current value.
+        while app.node_edit.as_ref().unwrap().current_key() != Some("parameter_tau") {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(app.node_edit.as_ref().unwrap().buffer.as_str(), "1.0");
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "1e-5");
+        while app.editing_node.is_some() {
+            press(&mut app, KeyCode::Enter);
+        }
+
+        assert_eq!(app.graph.nodes.len(), 1);
+        let node = &app.graph.nodes[0];
+        assert_eq!(node.label, "lane1.t2");
+        assert_eq!(node.values.get("parameter_tau"), Some(&toml::Value::Float(1e-5)));
+        assert_eq!(node.values.get("in").and_then(|v| v.as_str()), Some("feed"));
+    }
+
+    #[test]
+    fn deleting_a_node_cascades_to_edges_and_neighbour_io() {
+        let mut app = App::new();
+        for (id, label) in [(1, "a"), (2, "b"), (3, "c")] {
+            app.graph.add_node(NodeInstance::new(id, "cstr".to_string(), label.to_string()));
+        }
+        for (from, to, label) in [(1, 2, "a_out"), (2, 3, "b_out")] {
+            app.in_outs = [from, to];
+            app.label.set(label);
+            app.add_edge();
+        }
+        assert_eq!(
+            app.graph.get_node(1).unwrap().values.get("out"),
+            Some(&toml::Value::Array(vec![toml::Value::String("a_out".to_string())]))
+        );
+
+        app.delete_node(2);
+        assert!(app.graph.get_node(2).is_none());
+        assert!(app.graph.edges.is_empty());
+        assert!(!app.graph.get_node(1).unwrap().values.contains_key("out"));
+        assert!(!app.graph.get_node(3).unwrap().values.contains_key("in"));
+
+        // Ids keep increasing past deleted edges instead of colliding.
+        app.in_outs = [1, 3];
+        app.label.set("a_c");
+        app.add_edge();
+        app.in_outs = [3, 1];
+        app.label.set("c_a");
+        app.add_edge();
+        app.delete_edge(1);
+        app.in_outs = [1, 3];
+        app.label.set("again");
+        app.add_edge();
+        let ids: Vec<u64> = app.graph.edges.iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        // Undo walks back through the edge edits and then the cascading delete.
+        for _ in 0..5 {
+            app.undo();
+        }
+        assert!(app.graph.get_node(2).is_some());
+        assert_eq!(app.graph.edges.len(), 2);
+        assert_eq!(
+            app.graph.get_node(3).unwrap().values.get("in"),
+            Some(&toml::Value::Array(vec![toml::Value::String("b_out".to_string())]))
+        );
+    }
+
+    #[test]
+    fn saving_clears_dirty_state_and_undo_makes_it_dirty_again() {
+        let name = format!("graph-tui-save-{}.toml", std::process::id());
+        let path = std::env::temp_dir().join(name).to_string_lossy().to_string();
+        let mut app = App::new();
+        assert!(!app.is_dirty());
+
+        for _ in 0..2 {
+            app.label.set("lane1.t1");
+            app.add_node();
+        }
+        assert!(app.is_dirty());
+
+        // Without a path, saving asks for one first.
+        app.current_screen = CurrentScreen::GraphEditor;
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.current_screen, CurrentScreen::SaveAs);
+        type_text(&mut app, &path);
+        press(&mut app, KeyCode::Enter);
+
+        // Duplicate labels are an error, so validation blocks the write until `W`.
+        assert_eq!(app.current_screen, CurrentScreen::Diagnostics);
+        assert_eq!(app.pending_save.as_deref(), Some(path.as_str()));
+        assert!(app.is_dirty());
+        press(&mut app, KeyCode::Char('W'));
+        assert_eq!(app.current_screen, CurrentScreen::GraphEditor);
+        assert_eq!(app.file_path.as_deref(), Some(path.as_str()));
+        assert!(!app.is_dirty());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("lane1.t1"));
+
+        app.undo();
+        assert!(app.is_dirty());
+        app.redo();
+        assert!(!app.is_dirty());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn each_move_session_undoes_in_one_step() {
+        let mut app = App::new();
+        app.graph.add_node(NodeInstance::new(1, "cstr".to_string(), "a".to_string()));
+        app.node_catalog.set_node_coords(&mut app.graph.nodes[0], 0.0, 0.0);
+        app.current_screen = CurrentScreen::GraphEditor;
+        let x = |app: &App| app.node_catalog.node_coords(&app.graph.nodes[0]).unwrap().0;
+
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Char('m'));
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        let first = x(&app);
+        // Leaving the canvas ends the session, and so does switching move mode off.
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('c'));
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Char('m'));
+        press(&mut app, KeyCode::Char('m'));
+        press(&mut app, KeyCode::Right);
+
+        app.undo();
+        assert_eq!(x(&app), first + app.move_tool.step);
+        app.undo();
+        assert_eq!(x(&app), first);
+        app.undo();
+        assert_eq!(x(&app), 0.0);
+    }
+
+    #[test]
+    fn animated_force_layout_settles_into_one_undo_step() {
+        let mut app = App::new();
+        for (id, label) in [(1, "a"), (2, "b"), (3, "c")] {
+            app.graph.add_node(NodeInstance::new(id, "cstr".to_string(), label.to_string()));
+        }
+        app.graph.add_edge(Edge { id: 1, from: 1, to: 2, label: "s1".to_string() });
+        app.current_screen = CurrentScreen::Canvas;
+
+        press(&mut app, KeyCode::Char('R'));
+        assert!(app.force_layout.is_some());
+        assert_eq!(app.status.as_deref(), Some("Relaxing layout… <Space> to stop"));
+        // The view is fitted once up front and then left alone until the layout settles.
+        let fitted = app.viewport.bounds();
+        app.on_tick();
+        assert_eq!(app.viewport.bounds(), fitted);
+        while app.force_layout.is_some() {
+            app.on_tick();
+        }
+        assert!(app.graph.nodes.iter().all(|node| app.node_catalog.node_coords(node).is_some()));
+        assert_eq!(app.status.as_deref(), Some("Relaxed 3 node(s)"));
+
+        app.undo();
+        assert!(app.graph.nodes.iter().all(|node| app.node_catalog.node_coords(node).is_none()));
+
+        press(&mut app, KeyCode::Char('R'));
+        press(&mut app, KeyCode::Char(' '));
+        assert!(app.force_layout.is_none());
+        assert_eq!(app.status.as_deref(), Some("Relaxed 3 node(s)"));
+        app.undo();
+        assert!(app.graph.nodes.iter().all(|node| app.node_catalog.node_coords(node).is_none()));
+    }
+
+    #[test]
+    fn palette_lists_screen_actions_and_runs_the_selection() {
+        let mut app = App::new();
+        app.current_screen = CurrentScreen::GraphEditor;
+        app.on_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert_eq!(app.current_screen, CurrentScreen::Palette);
+
+        let actions = app.palette_matches();
+        assert!(actions.contains(&Action::AddEdge));
+        assert!(!actions.contains(&Action::OpenPalette));
+        assert!(!actions.contains(&Action::ZoomIn), "canvas actions stay on the canvas");
+
+        // Typed letters filter rather than trigger their bindings.
+        type_text(&mut app, "add edg");
+        assert_eq!(app.current_screen, CurrentScreen::Palette);
+        assert_eq!(app.palette_matches().first(), Some(&Action::AddEdge));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.current_screen, CurrentScreen::EdgeEditor);
+
+        press(&mut app, KeyCode::Esc);
+        app.on_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.current_screen, CurrentScreen::GraphEditor);
+
+        // Writing despite errors is only offered while a save is held back.
+        app.current_screen = CurrentScreen::Diagnostics;
+        app.on_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL));
+        assert!(!app.palette_matches().contains(&Action::WriteAnyway));
+        app.pending_save = Some("units.toml".to_string());
+        assert!(app.palette_matches().contains(&Action::WriteAnyway));
+    }
+
+    #[test]
+    fn node_editor_tabs_between_params_and_reviews_before_creating() {
+        let mut app = App::new();
+        app.current_screen = CurrentScreen::GraphEditor;
+        press(&mut app, KeyCode::Char('n'));
+        while app.current_type_name() != Some("cstr") {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "r1");
+        press(&mut app, KeyCode::Enter);
+
+        let current = |app: &App| {
+            app.node_edit.as_ref().unwrap().current_key().unwrap().to_string()
+        };
+        let first = current(&app);
+        while current(&app) != "model_id" {
+            press(&mut app, KeyCode::Tab);
+        }
+        type_text(&mut app, "m7");
+        press(&mut app, KeyCode::Tab);
+        // Tab wraps from the last param back to the first.
+        let last = app.node_edit.as_ref().unwrap().param_keys.last().unwrap().clone();
+        while current(&app) != last {
+            press(&mut app, KeyCode::Down);
+        }
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(current(&app), first);
+
+        let reviewing = |app: &App| {
+            matches!(app.currently_editing, Some(CurrentlyEditing::Node(NodeEditorMode::Review)))
+        };
+        while !reviewing(&app) {
+            press(&mut app, KeyCode::Enter);
         }
-        Err(error) => {
-            return Err(error.to_string());
+        assert!(app.graph.nodes.is_empty(), "nothing is created before the review is confirmed");
+        let review: HashMap<String, ParamStatus> = app.param_review().into_iter().collect();
+        assert_eq!(review["name"], ParamStatus::Derived("r1".to_string()));
+        assert_eq!(review["model_id"], ParamStatus::Set("m7".to_string()));
+        assert_eq!(review["parameter_tau"], ParamStatus::Unset);
+        // Only the editable rows are selectable, and they come first in editor order.
+        let keys = app.node_edit.as_ref().unwrap().param_keys.clone();
+        let rows: Vec<String> = app.param_review().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(rows[..keys.len()], keys[..]);
+        assert!(rows[keys.len()..].iter().all(|key| !is_editable_param(key)));
+        for _ in 0..keys.len() + 1 {
+            press(&mut app, KeyCode::Down);
         }
+        assert_eq!(current(&app), last);
+        while current(&app) != "model_id" {
+            press(&mut app, KeyCode::Up);
+        }
+
+        // Tab leaves the review to edit the highlighted param; Enter returns to it.
+        press(&mut app, KeyCode::Tab);
+        let editing = &app.currently_editing;
+        assert!(matches!(editing, Some(CurrentlyEditing::Node(NodeEditorMode::Param))));
+        type_text(&mut app, "8");
+        press(&mut app, KeyCode::Enter);
+        assert!(reviewing(&app));
+        assert_eq!(current(&app), "model_id");
+        assert_eq!(app.pending_node().values["model_id"].as_str(), Some("m78"));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.current_screen, CurrentScreen::GraphEditor);
+        assert_eq!(app.graph.nodes.len(), 1);
+        let node = &app.graph.nodes[0];
+        assert_eq!(node.label, "r1");
+        assert_eq!(node.values.get("model_id").and_then(|v| v.as_str()), Some("m78"));
+
+        // Editing writes exactly the node the review showed.
+        press(&mut app, KeyCode::Enter);
+        while !reviewing(&app) {
+            press(&mut app, KeyCode::Enter);
+        }
+        let reviewed = app.pending_node();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.graph.nodes[0], reviewed);
+    }
+
+    #[test]
+    fn text_fields_edit_mid_line_and_take_pastes() {
+        let mut app = App::new();
+        app.current_screen = CurrentScreen::GraphEditor;
+        press(&mut app, KeyCode::Char(':'));
+        app.insert_text("node cstr lane1.t9 junk\n");
+        app.on_key(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        assert_eq!(app.command_input.as_str(), "node cstr lane1.t9 ");
+        for _ in 0..2 {
+            press(&mut app, KeyCode::Left);
+        }
+        press(&mut app, KeyCode::Delete);
+        type_text(&mut app, "2");
+        press(&mut app, KeyCode::Home);
+        app.on_key(KeyEvent::new(KeyCode::Delete, KeyModifiers::CONTROL));
+        app.insert_text("node");
+        assert_eq!(app.command_input.as_str(), "node cstr lane1.t2 ");
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.current_screen, CurrentScreen::GraphEditor);
+        assert_eq!(app.graph.nodes[0].label, "lane1.t2");
     }
 }
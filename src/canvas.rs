@@ -0,0 +1,148 @@
+/// Pan/zoom state for the spatial canvas view.
+///
+/// Positions are in the graph's own `coords` space, where `y` grows downwards like on a
+/// process flow sheet. The canvas widget's `y` grows upwards, so `to_canvas` flips it.
+#[derive(Debug)]
+pub struct Viewport {
+    pub center_x: f64,
+    pub center_y: f64,
+    /// World units per terminal column. Rows are roughly twice as tall as columns, so
+    /// a row spans `2 * scale` units.
+    pub scale: f64,
+    /// Drawable size in cells, refreshed from the terminal every frame.
+    pub width: u16,
+    pub height: u16,
+}
+
+const DEFAULT_SCALE: f64 = 4.0;
+const ZOOM_STEP: f64 = 1.25;
+const PAN_FRACTION: f64 = 0.1;
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            center_x: 0.0,
+            center_y: 0.0,
+            scale: DEFAULT_SCALE,
+            width: 80,
+            height: 24,
+        }
+    }
+}
+
+impl Viewport {
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.width = width.max(1);
+        self.height = height.max(1);
+    }
+
+    /// Canvas `x_bounds` and `y_bounds` for the current view.
+    pub fn bounds(&self) -> ([f64; 2], [f64; 2]) {
+        let half_w = ((self.width as f64) * self.scale) / 2.0;
+        let half_h = (self.height as f64) * self.scale;
+        let (cx, cy) = to_canvas(self.center_x, self.center_y);
+        ([cx - half_w, cx + half_w], [cy - half_h, cy + half_h])
+    }
+
+    /// Pan by a fraction of the visible area; `dx`/`dy` are -1, 0 or 1 in world directions.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        let step_x = (self.width as f64) * self.scale * PAN_FRACTION;
+        let step_y = (self.height as f64) * 2.0 * self.scale * PAN_FRACTION;
+        self.center_x += dx * step_x;
+        self.center_y += dy * step_y;
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.scale /= ZOOM_STEP;
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.scale *= ZOOM_STEP;
+    }
+
+    /// Center on `points` and pick a scale that shows all of them with some margin.
+    pub fn fit(&mut self, points: &[(f64, f64)]) {
+        if points.is_empty() {
+            *self = Self {
+                width: self.width,
+                height: self.height,
+                ..Self::default()
+            };
+            return;
+        }
+        let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        self.center_x = (min_x + max_x) / 2.0;
+        self.center_y = (min_y + max_y) / 2.0;
+        let scale_x = (max_x - min_x) / (self.width as f64);
+        let scale_y = (max_y - min_y) / (2.0 * (self.height as f64));
+        let scale = scale_x.max(scale_y) * 1.3;
+        self.scale = if scale > 0.0 { scale } else { DEFAULT_SCALE };
+    }
+}
+
+/// Convert a position in `coords` space into canvas space.
+pub fn to_canvas(x: f64, y: f64) -> (f64, f64) {
+    (x, -y)
+}
+
+/// Keyboard nudging of the selected node on the canvas.
+#[derive(Debug)]
+pub struct MoveTool {
+    /// While active, the arrow keys move the selected node instead of panning.
+    pub active: bool,
+    /// Distance moved per key press, in `coords` units.
+    pub step: f64,
+    /// Snap positions to multiples of `step` after every move.
+    pub snap: bool,
+}
+
+impl Default for MoveTool {
+    fn default() -> Self {
+        Self {
+            active: false,
+            step: 10.0,
+            snap: false,
+        }
+    }
+}
+
+impl MoveTool {
+    pub fn nudge(&self, (x, y): (f64, f64), dx: f64, dy: f64) -> (f64, f64) {
+        let (x, y) = (x + dx * self.step, y + dy * self.step);
+        if self.snap {
+            (snap(x, self.step), snap(y, self.step))
+        } else {
+            (x, y)
+        }
+    }
+
+    pub fn grow_step(&mut self) {
+        self.step *= 2.0;
+    }
+
+    pub fn shrink_step(&mut self) {
+        self.step = (self.step / 2.0).max(0.125);
+    }
+}
+
+fn snap(value: f64, step: f64) -> f64 {
+    if step <= 0.0 { value } else { (value / step).round() * step }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nudge_moves_by_step_and_snaps_to_grid() {
+        let mut tool = MoveTool::default();
+        assert_eq!(tool.nudge((3.0, 4.0), 1.0, 0.0), (13.0, 4.0));
+        tool.snap = true;
+        assert_eq!(tool.nudge((3.0, 4.0), 1.0, -1.0), (10.0, -10.0));
+        tool.shrink_step();
+        assert_eq!(tool.nudge((3.0, 4.0), 0.0, 1.0), (5.0, 10.0));
+    }
+}
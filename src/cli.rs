@@ -0,0 +1,327 @@
+use crate::app::{
+    load_graph_from_path,
+    load_node_catalog_default,
+    load_node_catalog_from_path,
+    write_graph_to_path,
+    GraphItem,
+};
+use crate::command::{ run_script, script_writes };
+use crate::csv_io::{ graph_from_csv_paths, write_type_tables };
+use crate::dot::to_dot_string;
+use crate::mermaid::to_mermaid_string;
+use crate::graph::{ node_source_lines, Graph, GraphFormat };
+use crate::history::Transaction;
+use crate::layout::{ apply_force_layout, apply_layout };
+use crate::node_builder::NodeTypeCatalog;
+use crate::validate::{ describe_item, error_count, validate };
+
+/// Load `template`, or the built-in catalog when none is given. Unlike the interactive
+/// editor, headless commands fail on a bad template instead of falling back.
+pub fn load_catalog(template: Option<&str>) -> Result<NodeTypeCatalog, String> {
+    match template {
+        Some(path) =>
+            load_node_catalog_from_path(path).map_err(|error| {
+                format!("failed to load template {}: {}", path, error)
+            }),
+        None => Ok(load_node_catalog_default()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Graphviz DOT.
+    Dot,
+    /// Mermaid `flowchart LR`.
+    Mermaid,
+    /// Units file as TOML.
+    Toml,
+    /// Units file as JSON.
+    Json,
+    /// One `<type>.csv` per node type, written into the `-o` directory.
+    Csv,
+}
+
+/// Convert the units file at `input` and write it to `output`, or stdout when `None`.
+/// `group` wraps dotted label prefixes in subgraphs (Mermaid only).
+pub fn run_export(
+    input: &str,
+    catalog: &NodeTypeCatalog,
+    format: ExportFormat,
+    group: bool,
+    output: Option<&str>
+) -> Result<(), String> {
+    let graph = load_graph_from_path(input, None, catalog).map_err(|error| {
+        format!("failed to load {}: {}", input, error)
+    })?;
+    let rendered = match format {
+        ExportFormat::Dot => to_dot_string(&graph, catalog),
+        ExportFormat::Mermaid => to_mermaid_string(&graph, group),
+        ExportFormat::Toml => graph.to_format_string(GraphFormat::Toml, catalog)?,
+        ExportFormat::Json => graph.to_format_string(GraphFormat::Json, catalog)?,
+        ExportFormat::Csv => {
+            return export_csv_tables(&graph, catalog, output);
+        }
+    };
+    match output {
+        Some(path) =>
+            std::fs::write(path, rendered).map_err(|error| {
+                format!("failed to write {}: {}", path, error)
+            }),
+        None => {
+            print!("{}", rendered);
+            Ok(())
+        }
+    }
+}
+
+/// CSV is the one multi-file format, so it needs a directory rather than a file or stdout.
+fn export_csv_tables(
+    graph: &Graph,
+    catalog: &NodeTypeCatalog,
+    output: Option<&str>
+) -> Result<(), String> {
+    let Some(dir) = output else {
+        return Err("csv export writes one file per type; pass -o <dir>".to_string());
+    };
+    for path in write_type_tables(graph, catalog, dir)? {
+        eprintln!("wrote {}", path);
+    }
+    Ok(())
+}
+
+/// Build a graph from CSV tables and write it to `output` (as `format`, default: by
+/// extension). Row errors are printed to stderr and nothing is written; returns whether
+/// the import succeeded.
+pub fn run_import(
+    nodes: &str,
+    edges: Option<&str>,
+    output: &str,
+    format: Option<GraphFormat>,
+    catalog: &NodeTypeCatalog
+) -> Result<bool, String> {
+    let graph = match graph_from_csv_paths(nodes, edges, catalog) {
+        Ok(graph) => graph,
+        Err(errors) => {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            eprintln!("{} error(s); nothing written", errors.len());
+            return Ok(false);
+        }
+    };
+    write_graph_to_path(output, format, &graph, catalog).map_err(|error| {
+        format!("failed to write {}: {}", output, error)
+    })?;
+    eprintln!(
+        "{}: {} node(s), {} edge(s)",
+        output,
+        graph.nodes.len(),
+        graph.edges.len()
+    );
+    Ok(true)
+}
+
+/// Run the edit commands in `script` against `input` (or an empty graph), then write the
+/// result to `output`. A `w` without a path writes to `output` too. Every file is read
+/// and written as `format`, or by its extension when `None`. Nothing more is
+/// written once a line fails, and a script that would write nothing at all is refused
+/// before it runs.
+pub fn run_edit_script(
+    script: &str,
+    input: Option<&str>,
+    output: Option<&str>,
+    format: Option<GraphFormat>,
+    catalog: &NodeTypeCatalog
+) -> Result<(), String> {
+    let source = std::fs::read_to_string(script).map_err(|error| {
+        format!("failed to read {}: {}", script, error)
+    })?;
+    if output.is_none() && !script_writes(&source) {
+        return Err(format!("{}: no `w` command and no -o, so every edit would be lost", script));
+    }
+    let mut graph = match input {
+        Some(input) =>
+            load_graph_from_path(input, format, catalog).map_err(|error| {
+                format!("failed to load {}: {}", input, error)
+            })?,
+        None => Graph::new(),
+    };
+    let write = |path: Option<&str>, graph: &Graph| {
+        let path = path.or(output).ok_or("w needs a path when -o is not given")?;
+        write_graph_to_path(path, format, graph, catalog).map_err(|error| {
+            format!("failed to write {}: {}", path, error)
+        })?;
+        eprintln!("{}: {} node(s), {} edge(s)", path, graph.nodes.len(), graph.edges.len());
+        Ok(())
+    };
+    let count = run_script(&source, &mut graph, catalog, write).map_err(|error| {
+        format!("{}:{}", script, error)
+    })?;
+    if let Some(output) = output {
+        write(Some(output), &graph)?;
+    }
+    eprintln!("{}: ran {} command(s)", script, count);
+    Ok(())
+}
+
+/// Lay out `input` and write it to `output` (default: back to `input`), both as `format`
+/// or by extension. `force` selects the seeded force-directed layout instead of the
+/// layered one.
+pub fn run_layout(
+    input: &str,
+    output: Option<&str>,
+    all: bool,
+    force: Option<u64>,
+    format: Option<GraphFormat>,
+    catalog: &NodeTypeCatalog
+) -> Result<(), String> {
+    let mut graph = load_graph_from_path(input, format, catalog).map_err(|error| {
+        format!("failed to load {}: {}", input, error)
+    })?;
+    let mut tx = Transaction::new("layout");
+    let count = match force {
+        Some(seed) => apply_force_layout(&mut graph, &mut tx, catalog, seed, all),
+        None => apply_layout(&mut graph, &mut tx, catalog, all),
+    };
+    let output = output.unwrap_or(input);
+    write_graph_to_path(output, format, &graph, catalog).map_err(|error| {
+        format!("failed to write {}: {}", output, error)
+    })?;
+    eprintln!("{}: placed {} node(s)", output, count);
+    Ok(())
+}
+
+/// Import `path` (as `format`, default: by extension), print one
+/// `file:line: severity: target: message` line per diagnostic and a summary to stderr.
+/// Returns whether the file is free of errors.
+pub fn run_validate(
+    path: &str,
+    format: Option<GraphFormat>,
+    catalog: &NodeTypeCatalog
+) -> Result<bool, String> {
+    let raw = std::fs::read_to_string(path).map_err(|error| {
+        format!("failed to read {}: {}", path, error)
+    })?;
+    let format = format.unwrap_or_else(|| GraphFormat::for_path(path));
+    let graph = Graph::from_format_str(&raw, format, catalog).map_err(|error| {
+        format!("{}: {}", path, error)
+    })?;
+    // Line numbers are only tracked for TOML headers.
+    let lines = match format {
+        GraphFormat::Toml => node_source_lines(&raw, catalog)?,
+        GraphFormat::Json => Default::default(),
+    };
+    let diagnostics = validate(&graph, catalog);
+
+    for diagnostic in &diagnostics {
+        let location = match source_line(&graph, &lines, diagnostic.item) {
+            Some(line) => format!("{}:{}", path, line),
+            None => path.to_string(),
+        };
+        println!(
+            "{}: {}: {}: {}",
+            location,
+            diagnostic.severity.as_str(),
+            describe_item(&graph, diagnostic.item),
+            diagnostic.message
+        );
+    }
+    let errors = error_count(&diagnostics);
+    eprintln!(
+        "{}: {} node(s), {} edge(s), {} error(s), {} warning(s)",
+        path,
+        graph.nodes.len(),
+        graph.edges.len(),
+        errors,
+        diagnostics.len() - errors
+    );
+    Ok(errors == 0)
+}
+
+/// Edges have no table of their own, so they point at the consumer that names the
+/// stream (or the producer when the consumer is missing).
+fn source_line(
+    graph: &Graph,
+    lines: &std::collections::HashMap<usize, usize>,
+    item: GraphItem
+) -> Option<usize> {
+    match item {
+        GraphItem::Node(id) => lines.get(&id).copied(),
+        GraphItem::Edge(id) => {
+            let edge = graph.edges.iter().find(|e| e.id == id)?;
+            lines
+                .get(&(edge.to as usize))
+                .or_else(|| lines.get(&(edge.from as usize)))
+                .copied()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &str) -> String {
+        let name = format!("graph-tui-{}-{}", std::process::id(), name);
+        let path = std::env::temp_dir().join(name).to_string_lossy().to_string();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn validate_fails_on_errors_and_unreadable_files() {
+        let catalog = load_node_catalog_default();
+        // A recycle loop, so every `in`/`out` is connected.
+        let units = |second: &str| {
+            format!(
+                "[units]\n\n[[units.cstr]]\nname = \"t1\"\nin = \"b\"\nout = \"a\"\n\n\
+                 [[units.cstr]]\nname = \"{}\"\nin = \"a\"\nout = \"b\"\n",
+                second
+            )
+        };
+        let clean = temp_file("clean.toml", &units("t2"));
+        assert_eq!(run_validate(&clean, None, &catalog), Ok(true));
+
+        // `--format` wins over the extension.
+        let graph = load_graph_from_path(&clean, None, &catalog).unwrap();
+        let json = graph.to_format_string(GraphFormat::Json, &catalog).unwrap();
+        let units_json = temp_file("clean.units", &json);
+        assert_eq!(run_validate(&units_json, Some(GraphFormat::Json), &catalog), Ok(true));
+        assert!(run_validate(&units_json, None, &catalog).is_err());
+
+        // Duplicate labels are an error, so `validate` exits non-zero.
+        let duplicated = temp_file("duplicated.toml", &units("t1"));
+        assert_eq!(run_validate(&duplicated, None, &catalog), Ok(false));
+
+        let broken = temp_file("broken.toml", "[units\n");
+        assert!(run_validate(&broken, None, &catalog).unwrap_err().starts_with(&broken));
+        let missing = format!("{}.missing", clean);
+        assert!(run_validate(&missing, None, &catalog).unwrap_err().contains("failed to read"));
+
+        for path in [clean, units_json, duplicated, broken] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn edit_scripts_that_write_nothing_are_refused() {
+        let catalog = load_node_catalog_default();
+        let script = temp_file("edits.txt", "# no write\nnode cstr t1\n");
+        let error = run_edit_script(&script, None, None, None, &catalog).unwrap_err();
+        assert!(error.contains("no `w` command and no -o"), "{error}");
+
+        let output = format!("{}.toml", script);
+        assert_eq!(run_edit_script(&script, None, Some(&output), None, &catalog), Ok(()));
+        let graph = load_graph_from_path(&output, None, &catalog).unwrap();
+        assert_eq!(graph.nodes.len(), 1);
+
+        let written = format!("{}.w.toml", script);
+        std::fs::write(&script, format!("node cstr t1\nw {}\n", written)).unwrap();
+        assert_eq!(run_edit_script(&script, None, None, None, &catalog), Ok(()));
+        assert!(std::path::Path::new(&written).exists());
+
+        for path in [script, output, written] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
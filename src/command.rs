@@ -0,0 +1,426 @@
+use crate::app::{ connect, is_editable_param, parse_param_value, remove_node_cascading };
+use crate::edge::Edge;
+use crate::graph::Graph;
+use crate::history::Transaction;
+use crate::node_builder::{ NodeInstance, NodeTypeCatalog, NodeTypeDef };
+
+/// Command names, in the order completion offers them.
+pub const COMMANDS: &[&str] = &["node", "connect", "set", "rm", "w"];
+
+/// What a successful command asks of its caller.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// The graph was edited through the transaction; the text says how.
+    Edited(String),
+    /// Write the graph to the given path, or to the current file.
+    Write(Option<String>),
+}
+
+/// Run one command line against `graph`, recording edits in `tx`.
+///
+/// - `node <type> <label> [key=value ...]`
+/// - `connect <from> <to> <stream>`
+/// - `set <node> <key> <value>`
+/// - `rm <node>`
+/// - `w [path]`
+///
+/// Nodes are referred to by label, or by id when no label matches. Values go through
+/// `parse_param_value` against the template, exactly like the node editor. Words are split
+/// on whitespace; double quotes group them and may sit mid-word (`initial="[1, 2]"`).
+/// Everything is checked before the graph changes, so an `Err` leaves `graph` and `tx`
+/// untouched.
+pub fn execute(
+    line: &str,
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    catalog: &NodeTypeCatalog
+) -> Result<Outcome, String> {
+    let words = split_words(line)?;
+    let Some((command, args)) = words.split_first() else {
+        return Err("empty command".to_string());
+    };
+    match command.as_str() {
+        "node" => add_node(args, graph, tx, catalog),
+        "connect" => connect_nodes(args, graph, tx),
+        "set" => set_param(args, graph, tx, catalog),
+        "rm" => remove(args, graph, tx),
+        "w" =>
+            match args {
+                [] => Ok(Outcome::Write(None)),
+                [path] => Ok(Outcome::Write(Some(path.clone()))),
+                _ => Err("usage: w [path]".to_string()),
+            }
+        other => Err(format!("unknown command '{}' (try {})", other, COMMANDS.join(", "))),
+    }
+}
+
+/// Run a script of commands, one per line; blank lines and lines starting with `#` are
+/// skipped. `write` is called for every `w` with its optional path.
+///
+/// Stops at the first failing line, with the error prefixed by its 1-based line number.
+/// Returns how many commands ran.
+pub fn run_script(
+    source: &str,
+    graph: &mut Graph,
+    catalog: &NodeTypeCatalog,
+    mut write: impl FnMut(Option<&str>, &Graph) -> Result<(), String>
+) -> Result<usize, String> {
+    let mut count = 0;
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let at = |error: String| format!("{}: {}", index + 1, error);
+        let mut tx = Transaction::new(line);
+        match execute(line, graph, &mut tx, catalog).map_err(at)? {
+            Outcome::Edited(_) => {}
+            Outcome::Write(path) => write(path.as_deref(), graph).map_err(at)?,
+        }
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Whether any line of `source` is a `w` command.
+pub fn script_writes(source: &str) -> bool {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| line.split_whitespace().next() == Some("w"))
+}
+
+fn add_node(
+    args: &[String],
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    catalog: &NodeTypeCatalog
+) -> Result<Outcome, String> {
+    let [type_name, label, assignments @ ..] = args else {
+        return Err("usage: node <type> <label> [key=value ...]".to_string());
+    };
+    let def = type_def(catalog, type_name)?;
+    if graph.nodes.iter().any(|node| node.label == *label) {
+        return Err(format!("a node labelled '{}' already exists", label));
+    }
+    let mut node = NodeInstance::new(graph.get_new_node_id(), type_name.clone(), label.clone());
+    for assignment in assignments {
+        let (key, raw) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, got '{}'", assignment))?;
+        let value = parse_param(def, type_name, key, raw)?;
+        node.values.insert(key.to_string(), value);
+    }
+    tx.add_node(graph, node);
+    Ok(Outcome::Edited(format!("Added {} {}", type_name, label)))
+}
+
+fn connect_nodes(
+    args: &[String],
+    graph: &mut Graph,
+    tx: &mut Transaction
+) -> Result<Outcome, String> {
+    let [from, to, stream] = args else {
+        return Err("usage: connect <from> <to> <stream>".to_string());
+    };
+    let from_id = find_node(graph, from)?;
+    let to_id = find_node(graph, to)?;
+    let edge = Edge {
+        id: graph.get_new_edge_id(),
+        from: from_id as u64,
+        to: to_id as u64,
+        label: stream.clone(),
+    };
+    connect(graph, tx, edge);
+    Ok(Outcome::Edited(format!("Connected {} -> {} ({})", from, to, stream)))
+}
+
+fn set_param(
+    args: &[String],
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    catalog: &NodeTypeCatalog
+) -> Result<Outcome, String> {
+    let [node, key, value @ ..] = args else {
+        return Err("usage: set <node> <key> <value>".to_string());
+    };
+    if value.is_empty() {
+        return Err("usage: set <node> <key> <value>".to_string());
+    }
+    let id = find_node(graph, node)?;
+    let type_name = graph
+        .get_node(id)
+        .map(|node| node.type_.clone())
+        .unwrap_or_default();
+    let def = type_def(catalog, &type_name)?;
+    let value = parse_param(def, &type_name, key, &value.join(" "))?;
+    tx.update_node(graph, id, |node| {
+        node.values.insert(key.clone(), value);
+    });
+    Ok(Outcome::Edited(format!("Set {} on {}", key, node)))
+}
+
+fn remove(args: &[String], graph: &mut Graph, tx: &mut Transaction) -> Result<Outcome, String> {
+    let [node] = args else {
+        return Err("usage: rm <node>".to_string());
+    };
+    let id = find_node(graph, node)?;
+    let edges = graph.attached_edge_ids(id).len();
+    remove_node_cascading(graph, tx, id);
+    Ok(Outcome::Edited(format!("Removed {} and {} edge(s)", node, edges)))
+}
+
+fn type_def<'a>(catalog: &'a NodeTypeCatalog, type_name: &str) -> Result<&'a NodeTypeDef, String> {
+    catalog.nodes.types
+        .get(type_name)
+        .ok_or_else(|| format!("unknown node type '{}'", type_name))
+}
+
+fn parse_param(
+    def: &NodeTypeDef,
+    type_name: &str,
+    key: &str,
+    raw: &str
+) -> Result<toml::Value, String> {
+    if !is_editable_param(key) {
+        return Err(format!("'{}' follows the label and connections; it cannot be set", key));
+    }
+    let param = def.params
+        .get(key)
+        .ok_or_else(|| format!("{} has no param '{}'", type_name, key))?;
+    parse_param_value(raw, param).map_err(|error| format!("{}: {}", key, error))
+}
+
+/// Id of the node labelled `reference`, or with that id when no label matches.
+fn find_node(graph: &Graph, reference: &str) -> Result<usize, String> {
+    let mut labelled = graph.nodes.iter().filter(|node| node.label == reference);
+    match (labelled.next(), labelled.next()) {
+        (Some(node), None) => Ok(node.id),
+        (Some(_), Some(_)) => Err(format!("several nodes are labelled '{}'", reference)),
+        (None, _) =>
+            reference
+                .parse::<usize>()
+                .ok()
+                .filter(|id| graph.get_node(*id).is_some())
+                .ok_or_else(|| format!("no node '{}'", reference)),
+    }
+}
+
+/// Split on whitespace, with double quotes grouping words.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Complete the word at the end of `line`: command names, node types, node labels and
+/// param keys (`key=`) of the type in play.
+///
+/// Returns the line extended by the longest common prefix of the candidates (plus a space
+/// once the word is unambiguous), and the candidates themselves.
+pub fn complete(line: &str, graph: &Graph, catalog: &NodeTypeCatalog) -> (String, Vec<String>) {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        words.push("");
+    }
+    let partial = words.pop().unwrap_or_default();
+
+    let labels = || {
+        let mut labels: Vec<String> = graph.nodes
+            .iter()
+            .map(|node| node.label.clone())
+            .collect();
+        labels.sort();
+        labels.dedup();
+        labels
+    };
+    let keys_of = |type_name: &str| {
+        let mut keys: Vec<String> = catalog.nodes.types
+            .get(type_name)
+            .map(|def| {
+                def.params
+                    .keys()
+                    .filter(|key| is_editable_param(key))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        keys.sort();
+        keys
+    };
+    let type_of = |label: &str| {
+        find_node(graph, label)
+            .ok()
+            .and_then(|id| graph.get_node(id))
+            .map(|node| node.type_.clone())
+            .unwrap_or_default()
+    };
+
+    let candidates: Vec<String> = match (words.first().copied(), words.len()) {
+        (None, _) =>
+            COMMANDS.iter()
+                .map(|command| command.to_string())
+                .collect(),
+        (Some("node"), 1) => {
+            let mut types: Vec<String> = catalog.nodes.types.keys().cloned().collect();
+            types.sort();
+            types
+        }
+        (Some("node"), n) if n >= 3 =>
+            keys_of(words[1])
+                .into_iter()
+                .map(|key| format!("{}=", key))
+                .collect(),
+        (Some("connect"), 1 | 2) | (Some("set"), 1) | (Some("rm"), 1) => labels(),
+        (Some("set"), 2) => keys_of(&type_of(words[1])),
+        _ => Vec::new(),
+    };
+    let candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(partial))
+        .collect();
+
+    let mut completed = line[..line.len() - partial.len()].to_string();
+    match candidates.as_slice() {
+        [] => completed.push_str(partial),
+        [only] => {
+            completed.push_str(only);
+            if !only.ends_with('=') {
+                completed.push(' ');
+            }
+        }
+        [first, rest @ ..] => {
+            let mut common = first.as_str();
+            for candidate in rest {
+                let shared = common
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                common = &common[..shared];
+            }
+            completed.push_str(common);
+        }
+    }
+    (completed, candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::load_node_catalog_default;
+
+    #[test]
+    fn commands_edit_the_graph_and_reject_bad_arguments_untouched() {
+        let catalog = load_node_catalog_default();
+        let mut graph = Graph::new();
+        let mut tx = Transaction::new("script");
+        let mut run = |line: &str, graph: &mut Graph| execute(line, graph, &mut tx, &catalog);
+
+        run("node cstr lane1.t2", &mut graph).unwrap();
+        run(r#"node cstr "lane1.t3" parameter_tau=0.5 initial="[1, 2, 3]""#, &mut graph).unwrap();
+        let t3 = &graph.nodes[1];
+        assert_eq!(t3.label, "lane1.t3");
+        assert_eq!(t3.values.get("parameter_tau"), Some(&toml::Value::Float(0.5)));
+        assert_eq!(t3.values["initial"].as_array().map(|a| a.len()), Some(3));
+
+        run("connect lane1.t2 lane1.t3 lane1_t2_out", &mut graph).unwrap();
+        assert_eq!(graph.edges.len(), 1);
+        assert!(graph.nodes[0].values.contains_key("out"));
+
+        run("set lane1.t3 parameter_tau 1e-5", &mut graph).unwrap();
+        assert_eq!(graph.nodes[1].values["parameter_tau"], toml::Value::Float(1e-5));
+
+        let before = format!("{:?}", graph);
+        for bad in [
+            "set lane1.t3 parameter_tau fast",
+            "set lane1.t3 out x",
+            "node cstr lane1.t2",
+            "node tank lane1.t9",
+            "node cstr lane1.t9 nope=1",
+            "connect lane1.t2 ghost s",
+            "rm ghost",
+            "bogus",
+        ] {
+            assert!(run(bad, &mut graph).is_err(), "{bad}");
+        }
+        assert_eq!(format!("{:?}", graph), before);
+
+        assert_eq!(
+            run("rm lane1.t3", &mut graph).unwrap(),
+            Outcome::Edited("Removed lane1.t3 and 1 edge(s)".to_string())
+        );
+        assert!(graph.edges.is_empty());
+        assert_eq!(run("w out.toml", &mut graph).unwrap(), Outcome::Write(Some("out.toml".into())));
+    }
+
+    #[test]
+    fn scripts_skip_comments_write_on_w_and_report_the_failing_line() {
+        let catalog = load_node_catalog_default();
+        let script = "\
+            # two tanks in series\n\
+            node cstr lane1.t1 parameter_tau=2.0\n\
+            \n\
+            node cstr lane1.t2\n\
+            connect lane1.t1 lane1.t2 lane1_t1_out\n\
+            w site_a.toml\n\
+            rm lane1.t9\n\
+            node cstr never.added\n";
+        let mut graph = Graph::new();
+        let mut written = Vec::new();
+        let result = run_script(script, &mut graph, &catalog, |path, graph| {
+            written.push((path.map(str::to_string), graph.nodes.len()));
+            Ok(())
+        });
+        assert_eq!(result, Err("7: no node 'lane1.t9'".to_string()));
+        assert_eq!(written, vec![(Some("site_a.toml".to_string()), 2)]);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.edges.len(), 1);
+    }
+
+    #[test]
+    fn completes_commands_types_labels_and_param_keys() {
+        let catalog = load_node_catalog_default();
+        let mut graph = Graph::new();
+        graph.add_node(NodeInstance::new(1, "cstr".to_string(), "lane1.t2".to_string()));
+        graph.add_node(NodeInstance::new(2, "cstr".to_string(), "lane1.t3".to_string()));
+
+        assert_eq!(complete("co", &graph, &catalog).0, "connect ");
+        assert_eq!(complete("node cs", &graph, &catalog).0, "node cstr ");
+        let (line, labels) = complete("rm la", &graph, &catalog);
+        assert_eq!(line, "rm lane1.t");
+        assert_eq!(labels, vec!["lane1.t2", "lane1.t3"]);
+        let line = |partial: &str| complete(partial, &graph, &catalog).0;
+        assert_eq!(line("set lane1.t3 parameter_t"), "set lane1.t3 parameter_tau ");
+        assert_eq!(line("node cstr x parameter_t"), "node cstr x parameter_tau=");
+        assert!(complete("set lane1.t3 o", &graph, &catalog).1.is_empty(), "out is not settable");
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::app::{ connect, parse_param_value };
+use crate::edge::Edge;
+use crate::graph::{ render_inline_value, Graph };
+use crate::history::Transaction;
+use crate::node_builder::{ NodeInstance, NodeTypeCatalog };
+
+/// Build a graph from spreadsheet exports.
+///
+/// `nodes` needs `type` and `label` columns, an optional `id` column (blank cells get
+/// fresh ids) and one column per param; cells are parsed with `parse_param_value`
+/// against the row type's `ParamDef`, and blank cells leave the param unset. `edges`
+/// needs `from`, `to` and `label`, where endpoints are node ids or labels; edges are
+/// added with `connect`, so their endpoints' `in`/`out` values name the stream just as
+/// when they are drawn in the editor.
+///
+/// Every bad row is reported as `name:line: message`, using `nodes_name`/`edges_name`.
+pub fn graph_from_csv(
+    nodes: &str,
+    nodes_name: &str,
+    edges: Option<(&str, &str)>,
+    catalog: &NodeTypeCatalog
+) -> Result<Graph, Vec<String>> {
+    let mut errors = Vec::new();
+    let mut graph = Graph::new();
+    read_nodes(nodes, nodes_name, catalog, &mut graph, &mut errors);
+    if let Some((edges, edges_name)) = edges {
+        read_edges(edges, edges_name, &mut graph, &mut errors);
+    }
+    if errors.is_empty() { Ok(graph) } else { Err(errors) }
+}
+
+pub fn graph_from_csv_paths(
+    nodes_path: &str,
+    edges_path: Option<&str>,
+    catalog: &NodeTypeCatalog
+) -> Result<Graph, Vec<String>> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path).map_err(|error| vec![format!("{}: {}", path, error)])
+    };
+    let nodes = read(nodes_path)?;
+    let edges = match edges_path {
+        Some(path) => Some((read(path)?, path)),
+        None => None,
+    };
+    let edges = edges.as_ref().map(|(raw, path)| (raw.as_str(), *path));
+    graph_from_csv(&nodes, nodes_path, edges, catalog)
+}
+
+fn read_nodes(
+    raw: &str,
+    name: &str,
+    catalog: &NodeTypeCatalog,
+    graph: &mut Graph,
+    errors: &mut Vec<String>
+) {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(raw.as_bytes());
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => {
+            errors.push(format!("{}: {}", name, error));
+            return;
+        }
+    };
+    let column = |key: &str| headers.iter().position(|h| h == key);
+    let (Some(type_col), Some(label_col)) = (column("type"), column("label")) else {
+        errors.push(format!("{}:1: header needs `type` and `label` columns", name));
+        return;
+    };
+    let id_col = column("id");
+
+    // Ids are settled in a second pass so blank ones never collide with explicit ones.
+    let mut rows: Vec<(Option<usize>, NodeInstance)> = Vec::new();
+    let mut used = HashSet::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                errors.push(format!("{}: {}", name, error));
+                continue;
+            }
+        };
+        let line = record
+            .position()
+            .map(|p| p.line())
+            .unwrap_or(0);
+        let at = |message: String| format!("{}:{}: {}", name, line, message);
+
+        let type_name = record.get(type_col).unwrap_or_default();
+        let Some(type_def) = catalog.nodes.types.get(type_name) else {
+            errors.push(at(format!("type '{}' is not in the template", type_name)));
+            continue;
+        };
+        let id = match id_col.and_then(|col| record.get(col)).unwrap_or_default() {
+            "" => None,
+            raw =>
+                match raw.parse::<usize>() {
+                    Ok(id) if id > 0 && used.insert(id) => Some(id),
+                    Ok(id) if id > 0 => {
+                        errors.push(at(format!("duplicate id {}", id)));
+                        continue;
+                    }
+                    _ => {
+                        errors.push(at(format!("id '{}' is not a positive integer", raw)));
+                        continue;
+                    }
+                }
+        };
+
+        let label = record.get(label_col).unwrap_or_default().to_string();
+        let mut node = NodeInstance::new(0, type_name.to_string(), label);
+        for (col, cell) in record.iter().enumerate() {
+            if cell.is_empty() || col == type_col || col == label_col || Some(col) == id_col {
+                continue;
+            }
+            let key = &headers[col];
+            let Some(def) = type_def.params.get(key) else {
+                errors.push(at(format!("`{}` is not a {} param", key, type_name)));
+                continue;
+            };
+            match parse_param_value(cell, def) {
+                Ok(value) => {
+                    node.values.insert(key.to_string(), value);
+                }
+                Err(error) => errors.push(at(format!("`{}`: {}", key, error))),
+            }
+        }
+        rows.push((id, node));
+    }
+
+    let mut next = used.iter().max().copied().unwrap_or(0) + 1;
+    for (id, mut node) in rows {
+        node.id = id.unwrap_or_else(|| {
+            next += 1;
+            next - 1
+        });
+        graph.add_node(node);
+    }
+}
+
+fn read_edges(raw: &str, name: &str, graph: &mut Graph, errors: &mut Vec<String>) {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(raw.as_bytes());
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(error) => {
+            errors.push(format!("{}: {}", name, error));
+            return;
+        }
+    };
+    let column = |key: &str| headers.iter().position(|h| h == key);
+    let (Some(from_col), Some(to_col), Some(label_col)) = (
+        column("from"),
+        column("to"),
+        column("label"),
+    ) else {
+        errors.push(format!("{}:1: header needs `from`, `to` and `label` columns", name));
+        return;
+    };
+
+    let mut by_label: HashMap<&str, Vec<usize>> = HashMap::new();
+    for node in &graph.nodes {
+        by_label.entry(node.label.as_str()).or_default().push(node.id);
+    }
+    let resolve = |raw: &str| -> Result<u64, String> {
+        if let Ok(id) = raw.parse::<usize>() && graph.get_node(id).is_some() {
+            return Ok(id as u64);
+        }
+        match by_label.get(raw).map(|ids| ids.as_slice()) {
+            Some([id]) => Ok(*id as u64),
+            Some(_) => Err(format!("label '{}' matches several nodes; use the id", raw)),
+            None => Err(format!("no node with id or label '{}'", raw)),
+        }
+    };
+
+    let mut edges = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                errors.push(format!("{}: {}", name, error));
+                continue;
+            }
+        };
+        let line = record
+            .position()
+            .map(|p| p.line())
+            .unwrap_or(0);
+        let from = resolve(record.get(from_col).unwrap_or_default());
+        let to = resolve(record.get(to_col).unwrap_or_default());
+        let label = record.get(label_col).unwrap_or_default();
+        match (from, to) {
+            (Ok(from), Ok(to)) if !label.is_empty() => {
+                edges.push(Edge { id: 0, from, to, label: label.to_string() });
+            }
+            (Ok(_), Ok(_)) => errors.push(format!("{}:{}: edge has no label", name, line)),
+            (Err(error), _) | (_, Err(error)) => {
+                errors.push(format!("{}:{}: {}", name, line, error));
+            }
+        }
+    }
+    // A fresh graph has no history to record into.
+    let mut tx = Transaction::new("import");
+    for mut edge in edges {
+        edge.id = graph.get_new_edge_id();
+        connect(graph, &mut tx, edge);
+    }
+}
+
+/// One CSV per node type, as `(type, csv text)` sorted by type.
+///
+/// Rows are exactly what the units file would contain (from `to_template_toml_value`,
+/// so derived values are filled in), one per node in the template's unit order. Columns
+/// follow the type's `order`, then its remaining params sorted; params no node sets still
+/// get a column so every sheet of a type has the same shape. Strings are written raw,
+/// other values in TOML inline syntax, which the CSV importer parses back.
+pub fn type_tables(
+    graph: &Graph,
+    catalog: &NodeTypeCatalog
+) -> Result<Vec<(String, String)>, String> {
+    let root_key = catalog.format
+        .as_ref()
+        .map(|f| f.root.as_str())
+        .unwrap_or("units");
+    let doc = graph.to_template_toml_value(catalog);
+    let Some(root_table) = doc.get(root_key).and_then(|v| v.as_table()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut tables = Vec::new();
+    for (type_key, entries) in root_table {
+        let rows: Vec<&toml::map::Map<String, toml::Value>> = entries
+            .as_array()
+            .map(|a| a.iter().filter_map(|e| e.as_table()).collect())
+            .unwrap_or_default();
+
+        let type_def = catalog.nodes.types.get(type_key.as_str());
+        let mut columns: Vec<String> = type_def
+            .and_then(|def| def.order.clone())
+            .unwrap_or_default();
+        let mut rest: Vec<String> = type_def
+            .map(|def| def.params.keys().cloned().collect())
+            .unwrap_or_default();
+        for row in &rows {
+            rest.extend(row.keys().cloned());
+        }
+        rest.sort();
+        rest.dedup();
+        rest.retain(|key| !columns.contains(key));
+        columns.extend(rest);
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&columns).map_err(|e| e.to_string())?;
+        for row in rows {
+            let mut cells = Vec::new();
+            for key in &columns {
+                let cell = match row.get(key) {
+                    None => String::new(),
+                    Some(toml::Value::String(s)) => s.clone(),
+                    Some(value) => render_inline_value(value)?,
+                };
+                cells.push(cell);
+            }
+            writer.write_record(&cells).map_err(|e| e.to_string())?;
+        }
+        let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+        let text = String::from_utf8(bytes).map_err(|e| e.to_string())?;
+        tables.push((type_key.clone(), text));
+    }
+    Ok(tables)
+}
+
+/// Write `type_tables` into `dir` as `<type>.csv`, returning the written paths.
+pub fn write_type_tables(
+    graph: &Graph,
+    catalog: &NodeTypeCatalog,
+    dir: &str
+) -> Result<Vec<String>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    let mut written = Vec::new();
+    for (type_key, text) in type_tables(graph, catalog)? {
+        let path = std::path::Path::new(dir).join(format!("{}.csv", type_key));
+        let path = path.to_string_lossy().to_string();
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path, e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::load_node_catalog_default;
+
+    #[test]
+    fn csv_import_parses_cells_and_reports_bad_rows() {
+        let catalog = load_node_catalog_default();
+        let nodes =
+            "id,type,label,parameter_tau,initial,coords\n\
+             3,cstr,lane1.t1,0.5,\"1, 2, 3\",\"{ x = 1.0, y = 2.0 }\"\n\
+             ,sensor,lane1.s1,,,\n\
+             ,cstr,lane1.t2,,,\n";
+        let edges = "from,to,label\n3,lane1.s1,lane1_t1_out\nlane1.t2,3,lane1_t2_out\n";
+        let g = graph_from_csv(nodes, "nodes.csv", Some((edges, "edges.csv")), &catalog).unwrap();
+
+        let ids: Vec<usize> = g.nodes
+            .iter()
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(ids, vec![3, 4, 5]);
+        let t1 = g.get_node(3).unwrap();
+        assert_eq!(t1.values["parameter_tau"], toml::Value::Float(0.5));
+        assert_eq!(t1.values["initial"].as_array().map(|a| a.len()), Some(3));
+        assert_eq!(catalog.node_coords(t1), Some((1.0, 2.0)));
+        assert!(!g.get_node(4).unwrap().values.contains_key("parameter_tau"));
+        let pairs: Vec<(u64, u64)> = g.edges
+            .iter()
+            .map(|e| (e.from, e.to))
+            .collect();
+        assert_eq!(pairs, vec![(3, 4), (5, 3)]);
+        // Edges go through `connect`, like in the editor.
+        let streams = |id: usize, key: &str| g.get_node(id).unwrap().values[key].clone();
+        assert_eq!(streams(3, "out"), toml::Value::Array(vec!["lane1_t1_out".into()]));
+        assert_eq!(streams(3, "in"), toml::Value::Array(vec!["lane1_t2_out".into()]));
+
+        let nodes =
+            "type,label,parameter_tau,bogus\n\
+             cstr,a,fast,\n\
+             pump,b,,\n\
+             sensor,c,,1\n";
+        let edges = "from,to,label\na,z,s\n";
+        let errors = graph_from_csv(nodes, "n.csv", Some((edges, "e.csv")), &catalog).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "n.csv:2: `parameter_tau`: expected float".to_string(),
+                "n.csv:3: type 'pump' is not in the template".to_string(),
+                "n.csv:4: `bogus` is not a sensor param".to_string(),
+                "e.csv:2: no node with id or label 'z'".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn type_tables_follow_order_and_fill_derived_values() {
+        let catalog = load_node_catalog_default();
+        let nodes =
+            "id,type,label,parameter_tau,initial\n\
+             1,cstr,lane1.t1,0.5,\"1, 2\"\n\
+             2,sensor,lane1.s1,,\n";
+        let edges = "from,to,label\n1,2,lane1_t1_out\n";
+        let g = graph_from_csv(nodes, "nodes.csv", Some((edges, "edges.csv")), &catalog).unwrap();
+
+        let tables = type_tables(&g, &catalog).unwrap();
+        let types: Vec<&str> = tables
+            .iter()
+            .map(|(t, _)| t.as_str())
+            .collect();
+        assert_eq!(types, vec!["cstr", "sensor"]);
+
+        let mut reader = csv::Reader::from_reader(tables[0].1.as_bytes());
+        let header = reader.headers().unwrap().clone();
+        let header: Vec<&str> = header.iter().collect();
+        assert_eq!(&header[..2], &["name", "coords"]);
+        let mut rest = header[2..].to_vec();
+        rest.sort();
+        assert_eq!(rest, header[2..].to_vec(), "params after `order` are sorted");
+        let row = reader.records().next().unwrap().unwrap();
+        let cell = |key: &str| row[header.iter().position(|h| *h == key).unwrap()].to_string();
+        assert_eq!(cell("name"), "lane1.t1");
+        assert_eq!(cell("out"), "lane1_t1_out", "derived from the edge");
+        assert_eq!(cell("parameter_tau"), "0.5");
+        assert_eq!(cell("initial"), "[1.0, 2.0]");
+        assert_eq!(cell("in"), "");
+        assert!(tables[1].1.contains("lane1.s1"));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::graph::Graph;
+use crate::node_builder::NodeTypeCatalog;
+
+/// Render `graph` as a Graphviz `digraph`.
+///
+/// Nodes are `n<id>`, labelled with their label and type and styled from the type's
+/// `style` hint. Edges carry their stream name. Nodes with `coords` get a pinned `pos`
+/// (with `y` flipped, since Graphviz's `y` grows upwards), which `neato -n` honours and
+/// `dot` ignores.
+pub fn to_dot_string(graph: &Graph, catalog: &NodeTypeCatalog) -> String {
+    let name = catalog.format
+        .as_ref()
+        .map(|f| f.root.as_str())
+        .unwrap_or("units");
+
+    let mut out = String::new();
+    out.push_str(&format!("digraph {} {{\n", quote(name)));
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [fontname=\"Helvetica\"];\n");
+    out.push_str("    edge [fontname=\"Helvetica\", fontsize=10];\n");
+    if !graph.nodes.is_empty() {
+        out.push('\n');
+    }
+
+    for node in &graph.nodes {
+        let label = format!("{}\n{}", node.label, node.type_);
+        let mut attrs = vec![format!("label={}", quote(&label))];
+        let style = catalog.nodes.types
+            .get(node.type_.as_str())
+            .and_then(|def| def.style.as_ref());
+        if let Some(style) = style {
+            if let Some(shape) = &style.shape {
+                attrs.push(format!("shape={}", quote(shape)));
+            }
+            if let Some(color) = &style.color {
+                attrs.push(format!("color={}", quote(color)));
+            }
+            if let Some(fill) = &style.fill {
+                attrs.push("style=filled".to_string());
+                attrs.push(format!("fillcolor={}", quote(fill)));
+            }
+        }
+        if let Some((x, y)) = catalog.node_coords(node) {
+            attrs.push(format!("pos=\"{},{}!\"", x, 0.0 - y));
+        }
+        out.push_str(&format!("    n{} [{}];\n", node.id, attrs.join(", ")));
+    }
+
+    if !graph.edges.is_empty() {
+        out.push('\n');
+    }
+    for edge in &graph.edges {
+        out.push_str(
+            &format!("    n{} -> n{} [label={}];\n", edge.from, edge.to, quote(&edge.label))
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Quote a DOT ID, escaping backslashes, quotes and newlines.
+fn quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::load_node_catalog_default;
+    use crate::edge::Edge;
+    use crate::node_builder::NodeInstance;
+
+    #[test]
+    fn dot_has_styled_nodes_positions_and_labelled_edges() {
+        let catalog = load_node_catalog_default();
+        let mut g = Graph::new();
+        let mut tank = NodeInstance::new(1, "cstr".to_string(), "lane1.\"t1\"".to_string());
+        catalog.set_node_coords(&mut tank, 10.0, 20.0);
+        g.add_node(tank);
+        g.add_node(NodeInstance::new(2, "pump".to_string(), "p1".to_string()));
+        g.add_edge(Edge { id: 1, from: 1, to: 2, label: "lane1_t1_out".to_string() });
+
+        let dot = to_dot_string(&g, &catalog);
+        assert!(dot.starts_with("digraph \"units\" {\n"), "{dot}");
+        assert!(
+            dot.contains(
+                "    n1 [label=\"lane1.\\\"t1\\\"\\ncstr\", shape=\"box\", color=\"steelblue\", \
+                 style=filled, fillcolor=\"lightblue\", pos=\"10,-20!\"];\n"
+            ),
+            "{dot}"
+        );
+        // Types without a template entry (or style) fall back to Graphviz defaults.
+        assert!(dot.contains("    n2 [label=\"p1\\npump\"];\n"), "{dot}");
+        assert!(dot.contains("    n1 -> n2 [label=\"lane1_t1_out\"];\n"), "{dot}");
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,221 @@
+use crate::graph::Graph;
+use crate::keymap::{ Action, KeyContext, Keymap };
+
+/// Score `candidate` against `query` as a case-insensitive subsequence match.
+///
+/// Returns `None` when some query char cannot be matched in order. Higher is better:
+/// consecutive runs, matches at word starts and a match at the very start all score
+/// extra, and shorter candidates win ties. An empty query matches everything with 0.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    let mut score: i64 = 0;
+    let mut qi = 0;
+    let mut prev_match: Option<usize> = None;
+    for (i, c) in lower.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *c != query[qi] {
+            continue;
+        }
+        score += 1;
+        if i == 0 {
+            score += 8;
+        } else if !chars[i - 1].is_alphanumeric() {
+            score += 4;
+        }
+        if let Some(prev) = prev_match && prev + 1 == i {
+            score += 5;
+        }
+        prev_match = Some(i);
+        qi += 1;
+    }
+    if qi < query.len() {
+        return None;
+    }
+    Some(score * 16 - (chars.len() as i64))
+}
+
+/// Best score of `query` against any of `fields`.
+pub fn best_score<'a>(query: &str, fields: impl IntoIterator<Item = &'a str>) -> Option<i64> {
+    fields
+        .into_iter()
+        .filter_map(|field| fuzzy_score(query, field))
+        .max()
+}
+
+/// Searchable list of graph nodes, matched on label, type and id.
+#[derive(Debug, Default)]
+pub struct NodePicker {
+    pub query: String,
+    pub index: usize,
+}
+
+impl NodePicker {
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.index = 0;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.index = 0;
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        self.index = 0;
+        self.query.pop()
+    }
+
+    /// Node ids matching the query, best match first (ties broken by id).
+    pub fn matches(&self, graph: &Graph) -> Vec<usize> {
+        let mut scored: Vec<(i64, usize)> = graph.nodes
+            .iter()
+            .filter_map(|node| {
+                let id = node.id.to_string();
+                let fields = [node.label.as_str(), node.type_.as_str(), id.as_str()];
+                best_score(&self.query, fields).map(|score| (score, node.id))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        scored
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect()
+    }
+
+    pub fn move_selection(&mut self, delta: i32, match_count: usize) {
+        if match_count == 0 {
+            self.index = 0;
+            return;
+        }
+        let next = (self.index as i32) + delta;
+        self.index = next.clamp(0, (match_count as i32) - 1) as usize;
+    }
+
+    pub fn selected(&self, graph: &Graph) -> Option<usize> {
+        self.matches(graph).get(self.index).copied()
+    }
+}
+
+/// Searchable list of the actions of one key context, for the command palette, matched
+/// on title, keys.toml name and bound keys.
+#[derive(Debug, Default)]
+pub struct ActionPicker {
+    pub query: String,
+    pub index: usize,
+}
+
+impl ActionPicker {
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.index = 0;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.index = 0;
+    }
+
+    pub fn pop(&mut self) -> Option<char> {
+        self.index = 0;
+        self.query.pop()
+    }
+
+    /// Actions matching the query, best match first (ties keep binding order). The
+    /// palette itself and actions that are not `available` right now are never listed.
+    pub fn matches(
+        &self,
+        keymap: &Keymap,
+        context: KeyContext,
+        available: impl Fn(Action) -> bool
+    ) -> Vec<Action> {
+        let mut scored: Vec<(i64, Action)> = keymap
+            .actions(context)
+            .filter(|(action, _)| *action != Action::OpenPalette && available(*action))
+            .filter_map(|(action, keys)| {
+                let keys: Vec<String> = keys
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect();
+                let fields = [action.title(), action.name()]
+                    .into_iter()
+                    .chain(keys.iter().map(String::as_str));
+                best_score(&self.query, fields).map(|score| (score, action))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored
+            .into_iter()
+            .map(|(_, action)| action)
+            .collect()
+    }
+
+    pub fn move_selection(&mut self, delta: i32, match_count: usize) {
+        if match_count == 0 {
+            self.index = 0;
+            return;
+        }
+        let next = (self.index as i32) + delta;
+        self.index = next.clamp(0, (match_count as i32) - 1) as usize;
+    }
+
+    pub fn selected(
+        &self,
+        keymap: &Keymap,
+        context: KeyContext,
+        available: impl Fn(Action) -> bool
+    ) -> Option<Action> {
+        self.matches(keymap, context, available).get(self.index).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node_builder::NodeInstance;
+
+    #[test]
+    fn picker_matches_label_type_and_id() {
+        let mut graph = Graph::new();
+        for id in 1..=12 {
+            let type_ = if id % 2 == 0 { "sensor" } else { "cstr" };
+            graph.add_node(NodeInstance::new(id, type_.to_string(), format!("lane1.t{}", id)));
+        }
+        let mut picker = NodePicker::default();
+        for c in "t11".chars() {
+            picker.push(c);
+        }
+        assert_eq!(picker.selected(&graph), Some(11));
+
+        picker.clear();
+        picker.push('1');
+        picker.push('2');
+        assert_eq!(picker.selected(&graph), Some(12));
+
+        picker.clear();
+        for c in "sens".chars() {
+            picker.push(c);
+        }
+        assert!(picker.matches(&graph).iter().all(|id| id % 2 == 0));
+
+        picker.clear();
+        for c in "zzz".chars() {
+            picker.push(c);
+        }
+        assert_eq!(picker.selected(&graph), None);
+    }
+}
@@ -1,7 +1,16 @@
 use crate::edge::Edge;
 use crate::node_builder::NodeInstance;
-use crate::node_builder::{ NodeTypeCatalog, ParamDef, ParamSource, ParamType, RenderHint };
+use crate::analysis::flow_order;
+use crate::node_builder::{
+    NodeTypeCatalog,
+    ParamDef,
+    ParamSource,
+    ParamType,
+    RenderHint,
+    UnitOrder,
+};
 use serde::Serialize;
+use std::collections::HashMap;
 
 fn render_non_table_rhs(value: &toml::Value) -> Result<String, String> {
     // Leverage the TOML serializer for correct quoting/number formatting.
@@ -33,12 +42,16 @@ fn render_inline_table(table: &toml::map::Map<String, toml::Value>) -> Result<St
     Ok(format!("{{ {} }}", parts.join(", ")))
 }
 
+/// Render a value as it would appear on the right-hand side of an assignment.
+pub fn render_inline_value(value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::Table(table) => render_inline_table(table),
+        other => render_non_table_rhs(other),
+    }
+}
+
 fn render_assignment(key: &str, value: &toml::Value) -> Result<String, String> {
-    let rhs = match value {
-        toml::Value::Table(table) => render_inline_table(table)?,
-        other => render_non_table_rhs(other)?,
-    };
-    Ok(format!("{} = {}\n", key, rhs))
+    Ok(format!("{} = {}\n", key, render_inline_value(value)?))
 }
 #[derive(Debug, Serialize)]
 pub struct Graph {
@@ -53,10 +66,13 @@ fn incoming_edge_labels(graph: &Graph, node_id: usize) -> Vec<String> {
         .map(|e| (e.id, e.label.clone()))
         .collect();
     incoming.sort_by_key(|(id, _)| *id);
-    incoming
-        .into_iter()
-        .map(|(_, l)| l)
-        .collect()
+    let mut labels: Vec<String> = Vec::new();
+    for (_, label) in incoming {
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    labels
 }
 
 fn outgoing_edge_labels(graph: &Graph, node_id: usize) -> Vec<String> {
@@ -66,10 +82,13 @@ fn outgoing_edge_labels(graph: &Graph, node_id: usize) -> Vec<String> {
         .map(|e| (e.id, e.label.clone()))
         .collect();
     outgoing.sort_by_key(|(id, _)| *id);
-    outgoing
-        .into_iter()
-        .map(|(_, l)| l)
-        .collect()
+    let mut labels: Vec<String> = Vec::new();
+    for (_, label) in outgoing {
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+    labels
 }
 
 fn value_from_source(
@@ -132,6 +151,114 @@ fn apply_render_hint(value: toml::Value, def: Option<&ParamDef>) -> toml::Value
     value
 }
 
+#[derive(Clone, Copy)]
+enum StreamSide {
+    Incoming,
+    Outgoing,
+}
+
+/// Stream names a node declares on one side, ordered by the edge index the template
+/// expects them at (so recovered edge ids reproduce the same ordering on export).
+fn declared_streams(node: &NodeInstance, catalog: &NodeTypeCatalog, side: StreamSide) -> Vec<String> {
+    let Some(type_def) = catalog.nodes.types.get(node.type_.as_str()) else {
+        return Vec::new();
+    };
+    let mut indexed: Vec<(usize, String)> = Vec::new();
+    for (key, def) in &type_def.params {
+        let Some(value) = node.values.get(key) else {
+            continue;
+        };
+        let labels = stream_labels(value);
+        match (&def.source, side) {
+            (Some(ParamSource::IncomingEdgeLabels), StreamSide::Incoming)
+            | (Some(ParamSource::OutgoingEdgeLabels), StreamSide::Outgoing) => {
+                indexed.extend(labels.into_iter().enumerate());
+            }
+            (Some(ParamSource::IncomingEdgeLabel { index }), StreamSide::Incoming)
+            | (Some(ParamSource::OutgoingEdgeLabel { index }), StreamSide::Outgoing) => {
+                let idx = index.unwrap_or(0);
+                indexed.extend(labels.into_iter().map(|l| (idx, l)));
+            }
+            _ => {}
+        }
+    }
+    indexed.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    let mut out: Vec<String> = Vec::new();
+    for (_, label) in indexed {
+        if !out.contains(&label) {
+            out.push(label);
+        }
+    }
+    out
+}
+
+fn stream_labels(value: &toml::Value) -> Vec<String> {
+    match value {
+        toml::Value::String(s) => vec![s.clone()],
+        toml::Value::Array(items) =>
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// On-disk representation of a units file. Both carry the same template-shaped
+/// document; JSON simply spells it differently for tooling without a TOML parser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Toml,
+    Json,
+}
+
+impl GraphFormat {
+    /// Pick the format from the file extension, defaulting to TOML.
+    pub fn for_path(path: &str) -> Self {
+        let is_json = std::path::Path
+            ::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json { GraphFormat::Json } else { GraphFormat::Toml }
+    }
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "toml" => Ok(GraphFormat::Toml),
+            "json" => Ok(GraphFormat::Json),
+            other => Err(format!("unknown graph format '{}' (expected toml or json)", other)),
+        }
+    }
+}
+
+fn json_to_toml(value: &serde_json::Value) -> Result<toml::Value, String> {
+    Ok(match value {
+        serde_json::Value::Null => {
+            return Err("null has no TOML equivalent; leave the key out instead".to_string());
+        }
+        serde_json::Value::Bool(b) => toml::Value::Boolean(*b),
+        serde_json::Value::Number(n) =>
+            match n.as_i64() {
+                Some(i) => toml::Value::Integer(i),
+                None => toml::Value::Float(n.as_f64().unwrap_or_default()),
+            }
+        serde_json::Value::String(s) => toml::Value::String(s.clone()),
+        serde_json::Value::Array(items) =>
+            toml::Value::Array(items.iter().map(json_to_toml).collect::<Result<_, _>>()?),
+        serde_json::Value::Object(map) => {
+            let mut table = toml::map::Map::new();
+            for (k, v) in map {
+                table.insert(k.clone(), json_to_toml(v).map_err(|e| format!("{}: {}", k, e))?);
+            }
+            toml::Value::Table(table)
+        }
+    })
+}
+
 impl Graph {
     pub fn new() -> Self {
         Self {
@@ -148,6 +275,26 @@ impl Graph {
         self.edges.push(edge);
     }
 
+    /// Remove a node by id. Attached edges are left alone; see `App::delete_node`.
+    pub fn remove_node(&mut self, id: usize) -> Option<NodeInstance> {
+        let index = self.nodes.iter().position(|node| node.id == id)?;
+        Some(self.nodes.remove(index))
+    }
+
+    pub fn remove_edge(&mut self, id: u64) -> Option<Edge> {
+        let index = self.edges.iter().position(|edge| edge.id == id)?;
+        Some(self.edges.remove(index))
+    }
+
+    /// Ids of all edges that start or end at `node_id`.
+    pub fn attached_edge_ids(&self, node_id: usize) -> Vec<u64> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.from == (node_id as u64) || edge.to == (node_id as u64))
+            .map(|edge| edge.id)
+            .collect()
+    }
+
     pub fn get_node(&self, id: usize) -> Option<&NodeInstance> {
         self.nodes.iter().find(|node| node.id == id)
     }
@@ -164,6 +311,20 @@ impl Graph {
             .unwrap_or(0) + 1
     }
 
+    /// What export fills in for `def` on `node` when it carries no value of its own.
+    pub fn derived_value(&self, node: &NodeInstance, def: &ParamDef) -> Option<toml::Value> {
+        let source = def.source.as_ref()?;
+        value_from_source(self, node, source).map(|value| apply_render_hint(value, Some(def)))
+    }
+
+    pub fn get_new_edge_id(&self) -> u64 {
+        self.edges
+            .iter()
+            .map(|edge| edge.id)
+            .max()
+            .unwrap_or(0) + 1
+    }
+
     /// Serialize this graph into a TOML document driven entirely by `catalog`.
     ///
     /// The template controls:
@@ -192,11 +353,7 @@ impl Graph {
 
         let mut root_table = toml::map::Map::new();
 
-        // Deterministic output: preserve editing intent by ordering by node id.
-        let mut indices: Vec<usize> = (0..self.nodes.len()).collect();
-        indices.sort_by_key(|i| self.nodes[*i].id);
-
-        for i in indices {
+        for i in self.emit_order(catalog) {
             let node = &self.nodes[i];
             let mut table = toml::map::Map::new();
 
@@ -235,6 +392,20 @@ impl Graph {
         toml::Value::Table(root)
     }
 
+    /// Node indices in the order units are emitted: by id, or upstream to downstream when
+    /// the template's `format.unit_order` is `"flow"`.
+    fn emit_order(&self, catalog: &NodeTypeCatalog) -> Vec<usize> {
+        match catalog.unit_order() {
+            UnitOrder::Id => {
+                // Deterministic output: preserve editing intent by ordering by node id.
+                let mut indices: Vec<usize> = (0..self.nodes.len()).collect();
+                indices.sort_by_key(|i| self.nodes[*i].id);
+                indices
+            }
+            UnitOrder::Flow => flow_order(self),
+        }
+    }
+
     pub fn to_template_toml_string(&self, catalog: &NodeTypeCatalog) -> Result<String, String> {
         let root_key = catalog.format
             .as_ref()
@@ -274,52 +445,290 @@ impl Graph {
             .ok_or_else(|| format!("missing {} table", root_key))?;
         out.push_str(&format!("[{}]\n\n", root_key));
 
-        // Stable ordering: type key sorted, then insertion order within arrays.
-        let mut type_keys: Vec<&String> = root_table.keys().collect();
-        type_keys.sort();
+        // Which array each `[[root.type]]` block comes from, in output order. By id: type
+        // key sorted, then insertion order within arrays. By flow: the emit order, with
+        // types interleaved (TOML allows appending to an array of tables anywhere).
+        let mut sequence: Vec<&String> = Vec::new();
+        match catalog.unit_order() {
+            UnitOrder::Id => {
+                let mut type_keys: Vec<&String> = root_table.keys().collect();
+                type_keys.sort();
+                for type_key in type_keys {
+                    let count = root_table[type_key].as_array().map_or(0, |arr| arr.len());
+                    sequence.extend(std::iter::repeat_n(type_key, count));
+                }
+            }
+            UnitOrder::Flow => {
+                for i in self.emit_order(catalog) {
+                    if let Some((type_key, _)) = root_table.get_key_value(&self.nodes[i].type_) {
+                        sequence.push(type_key);
+                    }
+                }
+            }
+        }
 
-        for type_key in type_keys {
-            let Some(arr) = root_table.get(type_key).and_then(|v| v.as_array()) else {
-                continue;
-            };
+        let mut emitted: HashMap<&String, usize> = HashMap::new();
+        for type_key in sequence {
+            let position = emitted.entry(type_key).or_default();
+            let entry = root_table
+                .get(type_key)
+                .and_then(|v| v.as_array())
+                .and_then(|arr| arr.get(*position));
+            *position += 1;
 
             // Optional per-type ordering; otherwise sort keys.
             let order = catalog.nodes.types.get(type_key.as_str()).and_then(|t| t.order.clone());
 
-            for entry in arr {
-                let Some(table) = entry.as_table() else {
-                    continue;
-                };
-                out.push_str(&format!("[[{}.{}]]\n", root_key, type_key));
-
-                let mut keys: Vec<&String> = table.keys().collect();
-                match &order {
-                    Some(order) => {
-                        keys.sort_by_key(|k| {
-                            order
-                                .iter()
-                                .position(|o| o == k.as_str())
-                                .unwrap_or(usize::MAX)
-                        });
-                    }
-                    None => keys.sort(),
+            let Some(table) = entry.and_then(|e| e.as_table()) else {
+                continue;
+            };
+            out.push_str(&format!("[[{}.{}]]\n", root_key, type_key));
+
+            let mut keys: Vec<&String> = table.keys().collect();
+            match &order {
+                Some(order) => {
+                    keys.sort_by_key(|k| {
+                        order
+                            .iter()
+                            .position(|o| o == k.as_str())
+                            .unwrap_or(usize::MAX)
+                    });
                 }
+                None => keys.sort(),
+            }
+
+            for key in keys {
+                let value = &table[key];
+                out.push_str(&render_assignment(key, value)?);
+            }
+            out.push('\n');
+        }
 
-                for key in keys {
-                    let value = &table[key];
-                    out.push_str(&render_assignment(key, value)?);
+        Ok(out)
+    }
+
+    /// Rebuild a graph from a document shaped like `to_template_toml_value` output.
+    ///
+    /// Every entry under `[[<root>.<type>]]` becomes a `NodeInstance` (ids are assigned in
+    /// type-key order, then document order). Edges are recovered by running the template's
+    /// `source` hints in reverse: a stream listed by one node's outgoing param and another
+    /// node's incoming param becomes an edge between them. Values that the exporter would
+    /// derive identically are dropped from `values`; anything else (e.g. a feed with no
+    /// producer in the file) is kept verbatim so the document round-trips.
+    pub fn from_template_toml_value(
+        doc: &toml::Value,
+        catalog: &NodeTypeCatalog
+    ) -> Result<Self, String> {
+        let root_key = catalog.format
+            .as_ref()
+            .map(|f| f.root.as_str())
+            .unwrap_or("units");
+        let root_table = doc
+            .get(root_key)
+            .and_then(|v| v.as_table())
+            .ok_or_else(|| format!("missing {} table", root_key))?;
+
+        let mut graph = Graph::new();
+        for (type_key, entries) in root_table {
+            let entries = entries
+                .as_array()
+                .ok_or_else(|| format!("{}.{} must be an array of tables", root_key, type_key))?;
+            let type_def = catalog.nodes.types.get(type_key.as_str());
+            for entry in entries {
+                let table = entry
+                    .as_table()
+                    .ok_or_else(|| format!("{}.{} must be an array of tables", root_key, type_key))?;
+                let label_key = type_def
+                    .and_then(|def| {
+                        def.params
+                            .iter()
+                            .find(|(_, p)| matches!(p.source, Some(ParamSource::NodeLabel)))
+                            .map(|(k, _)| k.as_str())
+                    })
+                    .unwrap_or("name");
+                let label = table
+                    .get(label_key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+
+                let mut node = NodeInstance::new(
+                    graph.get_new_node_id(),
+                    type_key.clone(),
+                    label
+                );
+                for (k, v) in table {
+                    if type_def.is_some() && k == label_key {
+                        continue;
+                    }
+                    node.values.insert(k.clone(), v.clone());
                 }
-                out.push('\n');
+                graph.add_node(node);
             }
         }
 
+        graph.reconstruct_edges(catalog);
+        graph.drop_derivable_values(catalog);
+        Ok(graph)
+    }
+
+    pub fn from_template_toml_str(raw: &str, catalog: &NodeTypeCatalog) -> Result<Self, String> {
+        let doc: toml::Value = toml::from_str(raw).map_err(|e| e.to_string())?;
+        Self::from_template_toml_value(&doc, catalog)
+    }
+
+    /// JSON counterpart of `to_template_toml_string`: the same template-driven document
+    /// (root table, derived params, render hints), pretty-printed as JSON.
+    pub fn to_template_json_string(&self, catalog: &NodeTypeCatalog) -> Result<String, String> {
+        let doc = self.to_template_toml_value(catalog);
+        let mut out = serde_json::to_string_pretty(&doc).map_err(|e| e.to_string())?;
+        out.push('\n');
         Ok(out)
     }
 
-    // Back-compat wrapper (old name, now fully template-driven)
-    pub fn to_units_toml_string(&self, catalog: &NodeTypeCatalog) -> Result<String, String> {
-        self.to_template_toml_string(catalog)
+    pub fn from_template_json_str(raw: &str, catalog: &NodeTypeCatalog) -> Result<Self, String> {
+        let json: serde_json::Value = serde_json::from_str(raw).map_err(|e| e.to_string())?;
+        let doc = json_to_toml(&json)?;
+        Self::from_template_toml_value(&doc, catalog)
+    }
+
+    pub fn to_format_string(
+        &self,
+        format: GraphFormat,
+        catalog: &NodeTypeCatalog
+    ) -> Result<String, String> {
+        match format {
+            GraphFormat::Toml => self.to_template_toml_string(catalog),
+            GraphFormat::Json => self.to_template_json_string(catalog),
+        }
+    }
+
+    pub fn from_format_str(
+        raw: &str,
+        format: GraphFormat,
+        catalog: &NodeTypeCatalog
+    ) -> Result<Self, String> {
+        match format {
+            GraphFormat::Toml => Self::from_template_toml_str(raw, catalog),
+            GraphFormat::Json => Self::from_template_json_str(raw, catalog),
+        }
     }
+
+    fn reconstruct_edges(&mut self, catalog: &NodeTypeCatalog) {
+        let outgoing: Vec<(usize, Vec<String>)> = self.nodes
+            .iter()
+            .map(|node| (node.id, declared_streams(node, catalog, StreamSide::Outgoing)))
+            .collect();
+        let incoming: Vec<(usize, Vec<String>)> = self.nodes
+            .iter()
+            .map(|node| (node.id, declared_streams(node, catalog, StreamSide::Incoming)))
+            .collect();
+
+        let mut id: u64 = 1;
+        for (from, streams) in &outgoing {
+            for label in streams {
+                for (to, consumed) in &incoming {
+                    if !consumed.contains(label) {
+                        continue;
+                    }
+                    let duplicate = self.edges
+                        .iter()
+                        .any(|e| e.from == (*from as u64) && e.to == (*to as u64) && &e.label == label);
+                    if duplicate {
+                        continue;
+                    }
+                    self.add_edge(Edge {
+                        id,
+                        from: *from as u64,
+                        to: *to as u64,
+                        label: label.clone(),
+                    });
+                    id += 1;
+                }
+            }
+        }
+    }
+
+    fn drop_derivable_values(&mut self, catalog: &NodeTypeCatalog) {
+        let mut derivable: Vec<(usize, String)> = Vec::new();
+        for node in &self.nodes {
+            let Some(type_def) = catalog.nodes.types.get(node.type_.as_str()) else {
+                continue;
+            };
+            for (key, def) in &type_def.params {
+                let Some(source) = &def.source else {
+                    continue;
+                };
+                if matches!(source, ParamSource::NodeLabel) {
+                    continue;
+                }
+                let Some(existing) = node.values.get(key) else {
+                    continue;
+                };
+                let derived = value_from_source(self, node, source).map(|v|
+                    apply_render_hint(v, Some(def))
+                );
+                if derived.as_ref() == Some(&apply_render_hint(existing.clone(), Some(def))) {
+                    derivable.push((node.id, key.clone()));
+                }
+            }
+        }
+        for (id, key) in derivable {
+            if let Some(node) = self.nodes.iter_mut().find(|n| n.id == id) {
+                node.values.remove(&key);
+            }
+        }
+    }
+}
+
+/// 1-based line of each imported node's `[[root.type]]` header in `raw`, keyed by the id
+/// `from_template_toml_str` assigns it. Types written as inline arrays have no headers
+/// and are left out.
+pub fn node_source_lines(
+    raw: &str,
+    catalog: &NodeTypeCatalog
+) -> Result<HashMap<usize, usize>, String> {
+    let root_key = catalog.format
+        .as_ref()
+        .map(|f| f.root.as_str())
+        .unwrap_or("units");
+    let doc: toml::Value = toml::from_str(raw).map_err(|e| e.to_string())?;
+    let root_table = doc
+        .get(root_key)
+        .and_then(|v| v.as_table())
+        .ok_or_else(|| format!("missing {} table", root_key))?;
+
+    let mut headers: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, line) in raw.lines().enumerate() {
+        let Some(path) = line
+            .trim()
+            .strip_prefix("[[")
+            .and_then(|rest| rest.split("]]").next()) else {
+            continue;
+        };
+        let mut parts = path.splitn(2, '.').map(|part| part.trim().trim_matches('"'));
+        if parts.next() != Some(root_key) {
+            continue;
+        }
+        if let Some(type_key) = parts.next() {
+            headers.entry(type_key.to_string()).or_default().push(index + 1);
+        }
+    }
+
+    // Same walk as the importer: type keys in table order, entries in document order.
+    let mut lines = HashMap::new();
+    let mut next_id = 1;
+    for (type_key, entries) in root_table {
+        let count = entries.as_array().map(|a| a.len()).unwrap_or(0);
+        let found = headers.get(type_key).map(|v| v.as_slice()).unwrap_or(&[]);
+        if found.len() == count {
+            for (offset, line) in found.iter().enumerate() {
+                lines.insert(next_id + offset, *line);
+            }
+        }
+        next_id += count;
+    }
+    Ok(lines)
 }
 
 #[cfg(test)]
@@ -407,4 +816,155 @@ mod tests {
             "unexpected in value: {in_val:?}\nTOML:\n{out}"
         );
     }
+
+    #[test]
+    fn template_toml_roundtrips_through_importer() {
+        let catalog = default_catalog();
+        let raw = r#"
+[units]
+
+[[units.cstr]]
+name = "lane1.t1"
+coords = { x = 0.0, y = 0.0 }
+in = "lane1_feed"
+out = "lane1_t1_out"
+parameter_tau = 0.0000115
+
+[[units.splitter]]
+name = "lane1.split"
+in = "lane1_t1_out"
+out_a = "lane1_a"
+out_b = "lane1_b"
+
+[[units.sensor]]
+name = "lane1.a_sensor"
+in = "lane1_a"
+
+[[units.sensor]]
+name = "lane1.b_sensor"
+in = "lane1_b"
+"#;
+
+        let g = Graph::from_template_toml_str(raw, &catalog).expect("import succeeds");
+        assert_eq!(g.nodes.len(), 4);
+        assert_eq!(g.edges.len(), 3);
+
+        let t1 = g.nodes.iter().find(|n| n.label == "lane1.t1").expect("cstr node");
+        assert_eq!(t1.type_, "cstr");
+        // Derived from the edge, so not stored; the unmatched feed is kept verbatim.
+        assert!(!t1.values.contains_key("out"));
+        assert!(!t1.values.contains_key("name"));
+        assert_eq!(t1.values.get("in").and_then(|v| v.as_str()), Some("lane1_feed"));
+
+        let split = g.nodes.iter().find(|n| n.label == "lane1.split").expect("splitter node");
+        let outs: Vec<&str> = g.edges
+            .iter()
+            .filter(|e| e.from == (split.id as u64))
+            .map(|e| e.label.as_str())
+            .collect();
+        assert_eq!(outs, vec!["lane1_a", "lane1_b"]);
+
+        let out = g.to_template_toml_string(&catalog).unwrap();
+        let original: toml::Value = toml::from_str(raw).unwrap();
+        let reparsed: toml::Value = toml::from_str(&out).unwrap();
+        assert_eq!(original.get("units"), reparsed.get("units"), "TOML:\n{out}");
+    }
+
+    #[test]
+    fn template_json_roundtrips_to_the_same_graph() {
+        let catalog = default_catalog();
+        let raw = r#"
+[[units.cstr]]
+name = "lane1.t1"
+coords = { x = 0.0, y = 0.0 }
+in = "lane1_feed"
+out = "lane1_t1_out"
+
+[[units.sensor]]
+name = "lane1.t1_sensor"
+in = "lane1_t1_out"
+"#;
+        let g = Graph::from_template_toml_str(raw, &catalog).expect("import succeeds");
+
+        // JSON carries the same document, so it imports to the same graph.
+        let json = g.to_template_json_string(&catalog).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["units"]["cstr"][0]["out"], "lane1_t1_out", "JSON:\n{json}");
+        assert_eq!(parsed["units"]["cstr"][0]["coords"]["x"], 0.0);
+        let from_json = Graph::from_template_json_str(&json, &catalog).unwrap();
+        assert_eq!(from_json.nodes, g.nodes);
+        assert_eq!(from_json.edges, g.edges);
+
+        assert_eq!(GraphFormat::for_path("plant.JSON"), GraphFormat::Json);
+        assert_eq!(GraphFormat::for_path("plant.toml"), GraphFormat::Toml);
+    }
+
+    #[test]
+    fn source_lines_follow_headers_and_skip_inline_entries() {
+        let catalog = default_catalog();
+        let raw = r#"
+[units]
+source = [{ name = "feed", out = "s0" }]
+
+[[units.cstr]]
+name = "t"
+in = "s0"
+
+[[units.sensor]]
+name = "probe"
+
+[[units.cstr]]
+name = "t"
+"#;
+
+        let g = Graph::from_template_toml_str(raw, &catalog).expect("import succeeds");
+        let lines = node_source_lines(raw, &catalog).expect("lines resolve");
+        let feed = g.nodes.iter().find(|n| n.label == "feed").unwrap();
+        // Inline entries have no header line to point at.
+        assert!(!lines.contains_key(&feed.id));
+
+        let line_of = |label: &str| {
+            g.nodes
+                .iter()
+                .filter(|n| n.label == label)
+                .map(|n| lines[&n.id])
+                .collect::<Vec<_>>()
+        };
+        // Same-named nodes map to their own header, in document order.
+        assert_eq!(line_of("t"), vec![5, 12]);
+        assert_eq!(line_of("probe"), vec![9]);
+
+        assert!(node_source_lines("[other]\n", &catalog).is_err());
+    }
+
+    #[test]
+    fn flow_unit_order_interleaves_types_upstream_to_downstream() {
+        let mut catalog = default_catalog();
+        let mut g = Graph::new();
+        g.add_node(NodeInstance::new(1, "sensor".to_string(), "s".to_string()));
+        g.add_node(NodeInstance::new(2, "cstr".to_string(), "t2".to_string()));
+        g.add_node(NodeInstance::new(3, "source".to_string(), "feed".to_string()));
+        g.add_node(NodeInstance::new(4, "cstr".to_string(), "t1".to_string()));
+        for (id, (from, to)) in [(3, 4), (4, 2), (2, 1)].into_iter().enumerate() {
+            g.add_edge(Edge { id: (id as u64) + 1, from, to, label: format!("s{}", id) });
+        }
+        let names = |out: &str| -> Vec<String> {
+            out.lines()
+                .filter_map(|l| l.strip_prefix("name = "))
+                .map(|l| l.trim_matches('"').to_string())
+                .collect()
+        };
+
+        let by_id = g.to_template_toml_string(&catalog).unwrap();
+        assert_eq!(names(&by_id), vec!["t2", "t1", "s", "feed"]);
+
+        catalog.set_unit_order(UnitOrder::Flow);
+        let by_flow = g.to_template_toml_string(&catalog).unwrap();
+        assert_eq!(names(&by_flow), vec!["feed", "t1", "t2", "s"]);
+
+        // Interleaved arrays of tables still import to the same graph shape.
+        let back = Graph::from_template_toml_str(&by_flow, &catalog).unwrap();
+        assert_eq!(back.nodes.len(), 4);
+        assert_eq!(back.edges.len(), 3);
+    }
 }
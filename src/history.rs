@@ -0,0 +1,308 @@
+use crate::edge::Edge;
+use crate::graph::Graph;
+use crate::node_builder::NodeInstance;
+
+/// Maximum number of transactions kept on the undo stack.
+const MAX_UNDO: usize = 500;
+
+/// A single reversible mutation of a `Graph`.
+///
+/// Indices record where the item sat in `nodes`/`edges`, so undoing a removal puts it
+/// back in the same place and list order (and therefore export order) is preserved.
+#[derive(Debug, Clone)]
+pub enum Change {
+    InsertNode {
+        index: usize,
+        node: NodeInstance,
+    },
+    RemoveNode {
+        index: usize,
+        node: NodeInstance,
+    },
+    ReplaceNode {
+        index: usize,
+        before: NodeInstance,
+        after: NodeInstance,
+    },
+    InsertEdge {
+        index: usize,
+        edge: Edge,
+    },
+    RemoveEdge {
+        index: usize,
+        edge: Edge,
+    },
+}
+
+impl Change {
+    fn inverse(&self) -> Change {
+        match self {
+            Change::InsertNode { index, node } =>
+                Change::RemoveNode { index: *index, node: node.clone() },
+            Change::RemoveNode { index, node } =>
+                Change::InsertNode { index: *index, node: node.clone() },
+            Change::ReplaceNode { index, before, after } =>
+                Change::ReplaceNode {
+                    index: *index,
+                    before: after.clone(),
+                    after: before.clone(),
+                },
+            Change::InsertEdge { index, edge } =>
+                Change::RemoveEdge { index: *index, edge: edge.clone() },
+            Change::RemoveEdge { index, edge } =>
+                Change::InsertEdge { index: *index, edge: edge.clone() },
+        }
+    }
+
+    fn apply(&self, graph: &mut Graph) {
+        match self {
+            Change::InsertNode { index, node } => {
+                let index = (*index).min(graph.nodes.len());
+                graph.nodes.insert(index, node.clone());
+            }
+            Change::RemoveNode { index, .. } => {
+                if *index < graph.nodes.len() {
+                    graph.nodes.remove(*index);
+                }
+            }
+            Change::ReplaceNode { index, after, .. } => {
+                if let Some(slot) = graph.nodes.get_mut(*index) {
+                    *slot = after.clone();
+                }
+            }
+            Change::InsertEdge { index, edge } => {
+                let index = (*index).min(graph.edges.len());
+                graph.edges.insert(index, edge.clone());
+            }
+            Change::RemoveEdge { index, .. } => {
+                if *index < graph.edges.len() {
+                    graph.edges.remove(*index);
+                }
+            }
+        }
+    }
+}
+
+/// A group of changes that is undone and redone as one step.
+///
+/// Mutate the graph through the transaction's methods so that every side effect (such
+/// as the `in`/`out` values written when an edge is added) is captured.
+#[derive(Debug)]
+pub struct Transaction {
+    pub label: String,
+    changes: Vec<Change>,
+    /// Identifies the graph state this transaction leads to; assigned by `History`.
+    state: u64,
+}
+
+impl Transaction {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            changes: Vec::new(),
+            state: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn push(&mut self, graph: &mut Graph, change: Change) {
+        change.apply(graph);
+        self.changes.push(change);
+    }
+
+    pub fn add_node(&mut self, graph: &mut Graph, node: NodeInstance) {
+        let index = graph.nodes.len();
+        self.push(graph, Change::InsertNode { index, node });
+    }
+
+    pub fn remove_node(&mut self, graph: &mut Graph, id: usize) -> Option<NodeInstance> {
+        let index = graph.nodes.iter().position(|node| node.id == id)?;
+        let node = graph.nodes[index].clone();
+        self.push(graph, Change::RemoveNode { index, node: node.clone() });
+        Some(node)
+    }
+
+    /// Apply `f` to the node with `id`, recording the change only if it altered the node.
+    pub fn update_node(
+        &mut self,
+        graph: &mut Graph,
+        id: usize,
+        f: impl FnOnce(&mut NodeInstance)
+    ) -> bool {
+        let Some(index) = graph.nodes.iter().position(|node| node.id == id) else {
+            return false;
+        };
+        let before = graph.nodes[index].clone();
+        let mut after = before.clone();
+        f(&mut after);
+        if after == before {
+            return false;
+        }
+        self.push(graph, Change::ReplaceNode { index, before, after });
+        true
+    }
+
+    pub fn add_edge(&mut self, graph: &mut Graph, edge: Edge) {
+        let index = graph.edges.len();
+        self.push(graph, Change::InsertEdge { index, edge });
+    }
+
+    pub fn remove_edge(&mut self, graph: &mut Graph, id: u64) -> Option<Edge> {
+        let index = graph.edges.iter().position(|edge| edge.id == id)?;
+        let edge = graph.edges[index].clone();
+        self.push(graph, Change::RemoveEdge { index, edge: edge.clone() });
+        Some(edge)
+    }
+
+    fn undo(&self, graph: &mut Graph) {
+        for change in self.changes.iter().rev() {
+            change.inverse().apply(graph);
+        }
+    }
+
+    fn redo(&self, graph: &mut Graph) {
+        for change in &self.changes {
+            change.apply(graph);
+        }
+    }
+}
+
+/// Undo and redo stacks of applied transactions.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    last_state: u64,
+    /// State reached once every remaining undo entry is reverted.
+    base_state: u64,
+    /// Whether the last entry came from `record_merging` and may still take more.
+    merge_open: bool,
+}
+
+impl History {
+    /// Push an already-applied transaction. Empty transactions are dropped, and any new
+    /// edit invalidates the redo stack.
+    pub fn record(&mut self, mut tx: Transaction) {
+        if tx.is_empty() {
+            return;
+        }
+        tx.state = self.next_state();
+        self.redo.clear();
+        self.merge_open = false;
+        self.undo.push(tx);
+        if self.undo.len() > MAX_UNDO {
+            self.base_state = self.undo.remove(0).state;
+        }
+    }
+
+    /// Like `record`, but fold into the previous transaction when it has the same label,
+    /// so e.g. a run of nudges to one node undoes in a single step. `end_merge` closes
+    /// the run.
+    pub fn record_merging(&mut self, mut tx: Transaction) {
+        if tx.is_empty() {
+            return;
+        }
+        match self.undo.last_mut() {
+            Some(last) if self.merge_open && last.label == tx.label && self.redo.is_empty() => {
+                last.changes.append(&mut tx.changes);
+                self.last_state += 1;
+                last.state = self.last_state;
+            }
+            _ => self.record(tx),
+        }
+        self.merge_open = true;
+    }
+
+    /// Stop merging into the last entry; the next `record_merging` starts a new one.
+    pub fn end_merge(&mut self) {
+        self.merge_open = false;
+    }
+
+    fn next_state(&mut self) -> u64 {
+        self.last_state += 1;
+        self.last_state
+    }
+
+    /// Id of the current graph state. It changes with every recorded edit and returns to
+    /// an earlier value when undo/redo walks back to that state (0 = the initial graph),
+    /// which makes it suitable for dirty tracking.
+    pub fn state(&self) -> u64 {
+        self.undo
+            .last()
+            .map(|tx| tx.state)
+            .unwrap_or(self.base_state)
+    }
+
+    /// Revert the most recent transaction, returning its label.
+    pub fn undo(&mut self, graph: &mut Graph) -> Option<String> {
+        let tx = self.undo.pop()?;
+        tx.undo(graph);
+        let label = tx.label.clone();
+        self.redo.push(tx);
+        Some(label)
+    }
+
+    /// Re-apply the most recently undone transaction, returning its label.
+    pub fn redo(&mut self, graph: &mut Graph) -> Option<String> {
+        let tx = self.redo.pop()?;
+        tx.redo(graph);
+        let label = tx.label.clone();
+        self.undo.push(tx);
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo_restore_graph_state() {
+        let mut graph = Graph::new();
+        let mut history = History::default();
+
+        let mut tx = Transaction::new("add nodes");
+        tx.add_node(&mut graph, NodeInstance::new(1, "cstr".to_string(), "a".to_string()));
+        tx.add_node(&mut graph, NodeInstance::new(2, "cstr".to_string(), "b".to_string()));
+        history.record(tx);
+
+        let mut tx = Transaction::new("connect");
+        tx.add_edge(&mut graph, Edge { id: 1, from: 1, to: 2, label: "s".to_string() });
+        tx.update_node(&mut graph, 1, |node| {
+            node.values.insert("out".to_string(), toml::Value::String("s".to_string()));
+        });
+        history.record(tx);
+
+        let mut tx = Transaction::new("remove a");
+        tx.remove_edge(&mut graph, 1);
+        tx.remove_node(&mut graph, 1);
+        history.record(tx);
+        assert_eq!(graph.nodes.len(), 1);
+
+        let saved = history.state();
+        assert_eq!(history.undo(&mut graph).as_deref(), Some("remove a"));
+        assert_ne!(history.state(), saved);
+        assert_eq!(graph.nodes[0].id, 1, "node restored at its old position");
+        assert_eq!(graph.edges.len(), 1);
+
+        assert_eq!(history.undo(&mut graph).as_deref(), Some("connect"));
+        assert!(graph.edges.is_empty());
+        assert!(graph.nodes[0].values.is_empty());
+
+        assert_eq!(history.redo(&mut graph).as_deref(), Some("connect"));
+        assert_eq!(graph.edges.len(), 1);
+        assert!(graph.nodes[0].values.contains_key("out"));
+        assert_eq!(history.redo(&mut graph).as_deref(), Some("remove a"));
+        assert_eq!(history.state(), saved);
+        history.undo(&mut graph);
+
+        // A fresh edit drops the redo stack.
+        let mut tx = Transaction::new("rename");
+        tx.update_node(&mut graph, 2, |node| node.label = "c".to_string());
+        history.record(tx);
+        assert!(history.redo(&mut graph).is_none());
+    }
+}
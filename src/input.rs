@@ -0,0 +1,253 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::keymap::Action;
+
+/// Single-line text being edited, with a cursor.
+///
+/// The cursor is a byte offset that always sits on a grapheme boundary, so moving and
+/// deleting treat an emoji or an accented letter written with a combining mark as one
+/// character. Words, for the word-wise moves and deletes, are runs of non-whitespace, as
+/// with the shell's Ctrl-W.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TextInput {
+    value: String,
+    cursor: usize,
+}
+
+impl TextInput {
+    /// Input holding `value`, with the cursor at the end.
+    pub fn new(value: impl Into<String>) -> Self {
+        let value = value.into();
+        Self { cursor: value.len(), value }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn set(&mut self, value: impl Into<String>) {
+        *self = Self::new(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.value.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Insert pasted text at the cursor. Line breaks and tabs become spaces (a list copied
+    /// over several lines stays usable) and other control characters are dropped.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .trim_end_matches(['\r', '\n'])
+            .replace("\r\n", " ")
+            .chars()
+            .filter_map(|c| {
+                match c {
+                    '\n' | '\r' | '\t' => Some(' '),
+                    c if c.is_control() => None,
+                    c => Some(c),
+                }
+            })
+            .collect();
+        self.value.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    /// Delete the character before the cursor; `false` when there is none.
+    pub fn backspace(&mut self) -> bool {
+        let start = self.prev_boundary();
+        self.remove(start, self.cursor)
+    }
+
+    /// Delete the character under the cursor; `false` when there is none.
+    pub fn delete(&mut self) -> bool {
+        let end = self.next_boundary();
+        self.remove(self.cursor, end)
+    }
+
+    pub fn delete_word_back(&mut self) -> bool {
+        let start = self.word_start();
+        self.remove(start, self.cursor)
+    }
+
+    pub fn delete_word_forward(&mut self) -> bool {
+        let end = self.word_end();
+        self.remove(self.cursor, end)
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.value.len();
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_start();
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.word_end();
+    }
+
+    /// Perform a cursor or deletion action. Returns whether `action` is one of those
+    /// (whether or not it changed anything), so callers can fall back otherwise.
+    pub fn apply(&mut self, action: Action) -> bool {
+        match action {
+            Action::CursorLeft => self.move_left(),
+            Action::CursorRight => self.move_right(),
+            Action::LineStart => self.move_home(),
+            Action::LineEnd => self.move_end(),
+            Action::WordLeft => self.move_word_left(),
+            Action::WordRight => self.move_word_right(),
+            Action::DeleteChar => {
+                self.backspace();
+            }
+            Action::DeleteForward => {
+                self.delete();
+            }
+            Action::DeleteWord => {
+                self.delete_word_back();
+            }
+            Action::DeleteWordForward => {
+                self.delete_word_forward();
+            }
+            _ => {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// The part of the text that fits in `width` columns around the cursor, split at the
+    /// cursor. One column is kept for the cursor itself; wide characters count double.
+    pub fn visible(&self, width: usize) -> (&str, &str) {
+        let room = width.saturating_sub(1);
+        let (before, after) = self.value.split_at(self.cursor);
+        let mut start = before.len();
+        let mut used = 0;
+        for (i, grapheme) in before.grapheme_indices(true).rev() {
+            let w = grapheme.width();
+            if used + w > room {
+                break;
+            }
+            used += w;
+            start = i;
+        }
+        let mut end = 0;
+        for (i, grapheme) in after.grapheme_indices(true) {
+            let w = grapheme.width();
+            if used + w > room {
+                break;
+            }
+            used += w;
+            end = i + grapheme.len();
+        }
+        (&before[start..], &after[..end])
+    }
+
+    fn remove(&mut self, start: usize, end: usize) -> bool {
+        if start == end {
+            return false;
+        }
+        self.value.replace_range(start..end, "");
+        self.cursor = start;
+        true
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.value[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.value[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |grapheme| self.cursor + grapheme.len())
+    }
+
+    /// Start of the word before the cursor, skipping whitespace first.
+    fn word_start(&self) -> usize {
+        let before = &self.value[..self.cursor];
+        let trimmed = before.trim_end();
+        trimmed
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + trimmed[i..].chars().next().map_or(1, char::len_utf8))
+    }
+
+    /// End of the word after the cursor, skipping whitespace first.
+    fn word_end(&self) -> usize {
+        let after = &self.value[self.cursor..];
+        let skipped = after.len() - after.trim_start().len();
+        let rest = &after[skipped..];
+        let word = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.cursor + skipped + word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_mid_string_by_grapheme_and_word() {
+        let mut input = TextInput::new("[1.0, 2.0, 3.0]");
+        input.move_word_left();
+        input.move_word_left();
+        assert_eq!(input.visible(80), ("[1.0, ", "2.0, 3.0]"));
+        input.delete_word_forward();
+        input.insert_str("2.5,");
+        assert_eq!(input.as_str(), "[1.0, 2.5, 3.0]");
+        input.move_end();
+        input.delete_word_back();
+        assert_eq!(input.as_str(), "[1.0, 2.5, ");
+        assert!(input.backspace());
+        input.move_home();
+        assert!(!input.backspace());
+        assert!(input.delete());
+        assert_eq!(input.as_str(), "1.0, 2.5,");
+
+        // "e" + combining acute and a wide CJK character are one step each.
+        let mut input = TextInput::new("cafe\u{301}水");
+        input.move_left();
+        input.move_left();
+        input.insert('!');
+        assert_eq!(input.as_str(), "caf!e\u{301}水");
+        input.move_end();
+        assert!(input.backspace());
+        assert_eq!(input.as_str(), "caf!e\u{301}");
+
+        let mut input = TextInput::new("水水水水");
+        input.move_home();
+        input.move_right();
+        // Three columns: the cursor plus one wide character.
+        assert_eq!(input.visible(3), ("水", ""));
+        assert_eq!(input.visible(5), ("水", "水"));
+
+        input.set("");
+        input.insert_str("[1,\r\n 2]\n");
+        assert_eq!(input.as_str(), "[1,  2]");
+    }
+}
@@ -0,0 +1,627 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crossterm::event::{ KeyCode, KeyEvent, KeyModifiers };
+
+use crate::app::CurrentScreen;
+
+/// Something a key can do. Which actions mean anything depends on the `KeyContext`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    OpenGraph,
+    EditGraph,
+    OpenCanvas,
+    Validate,
+    Save,
+    SaveAs,
+    Quit,
+    Back,
+    AddNode,
+    AddEdge,
+    Up,
+    Down,
+    /// Enter: edit the selected node, jump to a diagnostic, accept a form field.
+    Confirm,
+    Delete,
+    Undo,
+    Redo,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    SelectNext,
+    SelectPrev,
+    ToggleMove,
+    ToggleSnap,
+    GrowStep,
+    ShrinkStep,
+    ZoomIn,
+    ZoomOut,
+    Fit,
+    LayoutMissing,
+    LayoutAll,
+    Relax,
+    StopLayout,
+    Rerun,
+    WriteAnyway,
+    ExitNow,
+    SaveAndExit,
+    Cancel,
+    NextField,
+    DeleteChar,
+    DeleteForward,
+    DeleteWord,
+    DeleteWordForward,
+    CursorLeft,
+    CursorRight,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    OpenPalette,
+    CommandLine,
+}
+
+/// `(name in keys.toml, action, title in the command palette)`; names are the snake_case
+/// variant names.
+const ACTION_NAMES: &[(&str, Action, &str)] = &[
+    ("open_graph", Action::OpenGraph, "Open graph overview"),
+    ("edit_graph", Action::EditGraph, "Edit graph"),
+    ("open_canvas", Action::OpenCanvas, "Open canvas"),
+    ("validate", Action::Validate, "Validate graph"),
+    ("save", Action::Save, "Save"),
+    ("save_as", Action::SaveAs, "Save as"),
+    ("quit", Action::Quit, "Quit"),
+    ("back", Action::Back, "Back"),
+    ("add_node", Action::AddNode, "Add node"),
+    ("add_edge", Action::AddEdge, "Add edge"),
+    ("up", Action::Up, "Select previous"),
+    ("down", Action::Down, "Select next"),
+    ("confirm", Action::Confirm, "Open selected"),
+    ("delete", Action::Delete, "Delete selected"),
+    ("undo", Action::Undo, "Undo"),
+    ("redo", Action::Redo, "Redo"),
+    ("pan_left", Action::PanLeft, "Pan/move left"),
+    ("pan_right", Action::PanRight, "Pan/move right"),
+    ("pan_up", Action::PanUp, "Pan/move up"),
+    ("pan_down", Action::PanDown, "Pan/move down"),
+    ("select_next", Action::SelectNext, "Select next node"),
+    ("select_prev", Action::SelectPrev, "Select previous node"),
+    ("toggle_move", Action::ToggleMove, "Toggle move mode"),
+    ("toggle_snap", Action::ToggleSnap, "Toggle snap to grid"),
+    ("grow_step", Action::GrowStep, "Increase move step"),
+    ("shrink_step", Action::ShrinkStep, "Decrease move step"),
+    ("zoom_in", Action::ZoomIn, "Zoom in"),
+    ("zoom_out", Action::ZoomOut, "Zoom out"),
+    ("fit", Action::Fit, "Fit graph to view"),
+    ("layout_missing", Action::LayoutMissing, "Lay out nodes without coords"),
+    ("layout_all", Action::LayoutAll, "Lay out all nodes"),
+    ("relax", Action::Relax, "Relax layout (force-directed)"),
+    ("stop_layout", Action::StopLayout, "Stop relaxing the layout"),
+    ("rerun", Action::Rerun, "Re-run validation"),
+    ("write_anyway", Action::WriteAnyway, "Write despite errors"),
+    ("exit_now", Action::ExitNow, "Exit without saving"),
+    ("save_and_exit", Action::SaveAndExit, "Save and exit"),
+    ("cancel", Action::Cancel, "Cancel"),
+    ("next_field", Action::NextField, "Next field"),
+    ("delete_char", Action::DeleteChar, "Delete character"),
+    ("delete_forward", Action::DeleteForward, "Delete character under cursor"),
+    ("delete_word", Action::DeleteWord, "Delete previous word"),
+    ("delete_word_forward", Action::DeleteWordForward, "Delete next word"),
+    ("cursor_left", Action::CursorLeft, "Cursor left"),
+    ("cursor_right", Action::CursorRight, "Cursor right"),
+    ("word_left", Action::WordLeft, "Cursor to previous word"),
+    ("word_right", Action::WordRight, "Cursor to next word"),
+    ("line_start", Action::LineStart, "Cursor to start"),
+    ("line_end", Action::LineEnd, "Cursor to end"),
+    ("open_palette", Action::OpenPalette, "Command palette"),
+    ("command_line", Action::CommandLine, "Command line (:node, :connect, :set, :rm, :w)"),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTION_NAMES.iter()
+            .find(|(_, action, _)| *action == self)
+            .map(|(name, _, _)| *name)
+            .unwrap_or("?")
+    }
+
+    pub fn title(self) -> &'static str {
+        ACTION_NAMES.iter()
+            .find(|(_, action, _)| *action == self)
+            .map(|(_, _, title)| *title)
+            .unwrap_or("?")
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ACTION_NAMES.iter()
+            .find(|(name, _, _)| *name == value)
+            .map(|(_, action, _)| *action)
+            .ok_or_else(|| format!("unknown action '{}'", value))
+    }
+}
+
+/// A group of screens sharing one set of bindings; one table in keys.toml each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyContext {
+    Main,
+    Graph,
+    Editor,
+    Canvas,
+    Diagnostics,
+    Exiting,
+    /// Text entry (node/edge editors, Save As): unbound characters are typed.
+    Form,
+}
+
+const CONTEXT_NAMES: &[(&str, KeyContext)] = &[
+    ("main", KeyContext::Main),
+    ("graph", KeyContext::Graph),
+    ("editor", KeyContext::Editor),
+    ("canvas", KeyContext::Canvas),
+    ("diagnostics", KeyContext::Diagnostics),
+    ("exiting", KeyContext::Exiting),
+    ("form", KeyContext::Form),
+];
+
+impl KeyContext {
+    /// Table name in keys.toml.
+    pub fn name(self) -> &'static str {
+        CONTEXT_NAMES.iter()
+            .find(|(_, context)| *context == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("?")
+    }
+
+    pub fn for_screen(screen: CurrentScreen) -> Self {
+        match screen {
+            CurrentScreen::Main => KeyContext::Main,
+            CurrentScreen::Graph => KeyContext::Graph,
+            CurrentScreen::GraphEditor => KeyContext::Editor,
+            CurrentScreen::Canvas => KeyContext::Canvas,
+            CurrentScreen::Diagnostics => KeyContext::Diagnostics,
+            CurrentScreen::Exiting => KeyContext::Exiting,
+            CurrentScreen::NodeEditor |
+            CurrentScreen::EdgeEditor |
+            CurrentScreen::SaveAs |
+            CurrentScreen::Palette |
+            CurrentScreen::CommandLine => KeyContext::Form,
+        }
+    }
+}
+
+/// One key, written in keys.toml as e.g. `"g"`, `"S"`, `"Ctrl-r"`, `"Enter"`, `"Up"` or
+/// `"Ctrl-Left"`.
+///
+/// Letters carry their case, so Shift is implied by an upper-case letter and ignored
+/// when matching character keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl KeyBinding {
+    fn new(code: KeyCode) -> Self {
+        Self { code, ctrl: false }
+    }
+
+    fn ctrl(c: char) -> Self {
+        Self::ctrl_key(KeyCode::Char(c))
+    }
+
+    fn ctrl_key(code: KeyCode) -> Self {
+        Self { code, ctrl: true }
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        self.ctrl == ctrl && self.code == key.code
+    }
+}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("Shift-Tab", KeyCode::BackTab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("Space", KeyCode::Char(' ')),
+];
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let named = |value: &str| {
+            NAMED_KEYS.iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(value))
+                .map(|(_, code)| *code)
+        };
+        if let Some(code) = named(value) {
+            return Ok(Self::new(code));
+        }
+        let (ctrl, rest) = match value.split_once('-') {
+            Some((modifier, rest)) if modifier.eq_ignore_ascii_case("ctrl") => (true, rest),
+            Some((modifier, rest)) if modifier.eq_ignore_ascii_case("shift") => {
+                let upper: String = rest.chars().flat_map(char::to_uppercase).collect();
+                return upper.parse();
+            }
+            _ => (false, value),
+        };
+        if ctrl && let Some(code) = named(rest) {
+            return Ok(Self::ctrl_key(code));
+        }
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if ctrl => Ok(Self::ctrl(c.to_ascii_lowercase())),
+            (Some(c), None) => Ok(Self::new(KeyCode::Char(c))),
+            _ => Err(format!("unknown key '{}'", value)),
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    /// Footer form: `g` shows as `G`, `S` as `Shift-S`, Ctrl-r as `Ctrl-R`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            KeyCode::Char(c) if self.ctrl => write!(f, "Ctrl-{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) if c.is_ascii_uppercase() => write!(f, "Shift-{}", c),
+            KeyCode::Char(c) if c.is_ascii_lowercase() => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            code => {
+                if self.ctrl {
+                    write!(f, "Ctrl-")?;
+                }
+                let name = NAMED_KEYS.iter()
+                    .find(|(_, named)| *named == code)
+                    .map(|(name, _)| *name)
+                    .unwrap_or("?");
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+/// Bindings per context, in priority order: the first binding of an action is the one
+/// shown in footers.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, Vec<(Action, Vec<KeyBinding>)>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use KeyContext as C;
+
+        let c = |c: char| KeyBinding::new(KeyCode::Char(c));
+        let k = KeyBinding::new;
+        let ctrl = KeyBinding::ctrl;
+        let table = [
+            (
+                C::Main,
+                vec![
+                    (OpenGraph, vec![c('g'), c('G')]),
+                    (Save, vec![c('s')]),
+                    (SaveAs, vec![c('S')]),
+                    (Quit, vec![c('q'), c('Q')]),
+                    (OpenPalette, vec![ctrl('p')])
+                ],
+            ),
+            (
+                C::Graph,
+                vec![
+                    (EditGraph, vec![c('e'), c('E')]),
+                    (OpenCanvas, vec![c('c'), c('C')]),
+                    (Validate, vec![c('v'), c('V')]),
+                    (Save, vec![c('s')]),
+                    (SaveAs, vec![c('S')]),
+                    (Back, vec![c('q'), c('Q')]),
+                    (OpenPalette, vec![ctrl('p')]),
+                    (CommandLine, vec![c(':')])
+                ],
+            ),
+            (
+                C::Editor,
+                vec![
+                    (AddNode, vec![c('n'), c('N')]),
+                    (AddEdge, vec![c('e'), c('E')]),
+                    (Up, vec![k(KeyCode::Up)]),
+                    (Down, vec![k(KeyCode::Down)]),
+                    (Confirm, vec![k(KeyCode::Enter)]),
+                    (Delete, vec![c('d'), c('D'), k(KeyCode::Delete)]),
+                    (OpenCanvas, vec![c('c'), c('C')]),
+                    (Validate, vec![c('v'), c('V')]),
+                    (Undo, vec![c('u')]),
+                    (Redo, vec![c('U'), ctrl('r')]),
+                    (Save, vec![c('s')]),
+                    (SaveAs, vec![c('S')]),
+                    (Back, vec![c('q'), c('Q')]),
+                    (OpenPalette, vec![ctrl('p')]),
+                    (CommandLine, vec![c(':')])
+                ],
+            ),
+            (
+                C::Canvas,
+                vec![
+                    (PanLeft, vec![c('h'), k(KeyCode::Left)]),
+                    (PanDown, vec![c('j'), k(KeyCode::Down)]),
+                    (PanUp, vec![c('k'), k(KeyCode::Up)]),
+                    (PanRight, vec![c('l'), k(KeyCode::Right)]),
+                    (ZoomIn, vec![c('+'), c('=')]),
+                    (ZoomOut, vec![c('-'), c('_')]),
+                    (Fit, vec![c('f'), c('F'), c('0')]),
+                    (SelectNext, vec![k(KeyCode::Tab)]),
+                    (SelectPrev, vec![k(KeyCode::BackTab)]),
+                    (ToggleMove, vec![c('m'), c('M')]),
+                    (ShrinkStep, vec![c('[')]),
+                    (GrowStep, vec![c(']')]),
+                    (ToggleSnap, vec![c('g'), c('G')]),
+                    (LayoutMissing, vec![c('L')]),
+                    (LayoutAll, vec![c('A')]),
+                    (Relax, vec![c('R')]),
+                    (StopLayout, vec![c(' ')]),
+                    (Undo, vec![c('u')]),
+                    (Redo, vec![c('U'), ctrl('r')]),
+                    (Save, vec![c('s')]),
+                    (SaveAs, vec![c('S')]),
+                    (Back, vec![c('q'), c('Q'), k(KeyCode::Esc)]),
+                    (OpenPalette, vec![ctrl('p')]),
+                    (CommandLine, vec![c(':')])
+                ],
+            ),
+            (
+                C::Diagnostics,
+                vec![
+                    (Up, vec![k(KeyCode::Up)]),
+                    (Down, vec![k(KeyCode::Down)]),
+                    (Confirm, vec![k(KeyCode::Enter)]),
+                    (Rerun, vec![c('r'), c('R')]),
+                    (WriteAnyway, vec![c('W')]),
+                    (Back, vec![k(KeyCode::Esc), c('q'), c('Q')]),
+                    (OpenPalette, vec![ctrl('p')])
+                ],
+            ),
+            (
+                C::Exiting,
+                vec![
+                    (ExitNow, vec![c('y')]),
+                    (SaveAndExit, vec![c('s')]),
+                    (Back, vec![c('n'), c('q'), k(KeyCode::Esc)])
+                ],
+            ),
+            (
+                // Letters are typed here, so `q` no longer closes the node and edge
+                // editors; Esc does.
+                C::Form,
+                vec![
+                    (Confirm, vec![k(KeyCode::Enter)]),
+                    (Cancel, vec![k(KeyCode::Esc)]),
+                    (NextField, vec![k(KeyCode::Tab)]),
+                    (Up, vec![k(KeyCode::Up)]),
+                    (Down, vec![k(KeyCode::Down)]),
+                    (DeleteChar, vec![k(KeyCode::Backspace)]),
+                    (DeleteForward, vec![k(KeyCode::Delete)]),
+                    (DeleteWord, vec![ctrl('w'), KeyBinding::ctrl_key(KeyCode::Backspace)]),
+                    (DeleteWordForward, vec![KeyBinding::ctrl_key(KeyCode::Delete)]),
+                    (CursorLeft, vec![k(KeyCode::Left)]),
+                    (CursorRight, vec![k(KeyCode::Right)]),
+                    (WordLeft, vec![KeyBinding::ctrl_key(KeyCode::Left)]),
+                    (WordRight, vec![KeyBinding::ctrl_key(KeyCode::Right)]),
+                    (LineStart, vec![k(KeyCode::Home), ctrl('a')]),
+                    (LineEnd, vec![k(KeyCode::End), ctrl('e')])
+                ],
+            ),
+        ];
+        Self { bindings: table.into_iter().collect() }
+    }
+}
+
+impl Keymap {
+    /// The action `key` triggers in `context`, if any.
+    pub fn action(&self, context: KeyContext, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .get(&context)?
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|binding| binding.matches(key)))
+            .map(|(action, _)| *action)
+    }
+
+    /// Every action of `context` with its keys, in binding order.
+    pub fn actions(&self, context: KeyContext) -> impl Iterator<Item = (Action, &[KeyBinding])> {
+        self.bindings
+            .get(&context)
+            .into_iter()
+            .flatten()
+            .map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    /// Keys bound to `action` in `context`, primary first.
+    pub fn keys(&self, context: KeyContext, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .get(&context)
+            .and_then(|actions| actions.iter().find(|(a, _)| *a == action))
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Footer text for `action`, e.g. `Shift-S`; `None` when it is unbound.
+    pub fn primary(&self, context: KeyContext, action: Action) -> Option<String> {
+        self.keys(context, action)
+            .first()
+            .map(|key| key.to_string())
+    }
+
+    /// The first key of `action` as it is typed (`y`, `S`, `Ctrl-R`), for prompts that
+    /// spell out answers in prose, where the footer's `Y` would read as upper case.
+    pub fn prompt_key(&self, context: KeyContext, action: Action) -> Option<String> {
+        let key = self.keys(context, action).first()?;
+        Some(match key.code {
+            KeyCode::Char(c) if !key.ctrl && c != ' ' => c.to_string(),
+            _ => key.to_string(),
+        })
+    }
+
+    /// Apply a keys.toml: one table per context, `action = "key"` or `action = ["k", ..]`.
+    ///
+    /// Listed actions replace their default keys in that context (an empty list unbinds
+    /// them); everything else keeps its default. A config that leaves a screen without
+    /// a key for `back`, `quit` or `cancel` is rejected, since nothing else leaves it.
+    pub fn from_toml_str(raw: &str) -> Result<Self, String> {
+        let table: toml::Table = toml::from_str(raw).map_err(|error| error.to_string())?;
+        let mut keymap = Keymap::default();
+        for (context_name, actions) in table {
+            let context = CONTEXT_NAMES.iter()
+                .find(|(name, _)| *name == context_name)
+                .map(|(_, context)| *context)
+                .ok_or_else(|| format!("unknown context [{}]", context_name))?;
+            let actions = actions
+                .as_table()
+                .ok_or_else(|| format!("[{}] must be a table", context_name))?;
+            for (action_name, keys) in actions {
+                let action: Action = action_name
+                    .parse()
+                    .map_err(|error| format!("[{}]: {}", context_name, error))?;
+                let at = |error: String| format!("[{}] {}: {}", context_name, action_name, error);
+                let keys = match keys {
+                    toml::Value::String(key) => vec![key.as_str()],
+                    toml::Value::Array(keys) =>
+                        keys
+                            .iter()
+                            .map(|key| key.as_str().ok_or("keys must be strings".to_string()))
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(at)?,
+                    _ => {
+                        return Err(at("expected a key or a list of keys".to_string()));
+                    }
+                };
+                let keys = keys
+                    .into_iter()
+                    .map(KeyBinding::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(at)?;
+                keymap.bind(context, action, keys);
+            }
+        }
+        for (context, actions) in &Keymap::default().bindings {
+            for (action, _) in actions {
+                let leaves = matches!(action, Action::Back | Action::Quit | Action::Cancel);
+                if leaves && keymap.keys(*context, *action).is_empty() {
+                    return Err(format!("[{}] {} needs a key", context.name(), action.name()));
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Replace the keys of `action` in `context`. The keys are taken away from any other
+    /// action of that context so a rebind cannot be shadowed by a default.
+    fn bind(&mut self, context: KeyContext, action: Action, keys: Vec<KeyBinding>) {
+        let actions = self.bindings.entry(context).or_default();
+        for (other, other_keys) in actions.iter_mut() {
+            if *other != action {
+                other_keys.retain(|key| !keys.contains(key));
+            }
+        }
+        match actions.iter_mut().find(|(a, _)| *a == action) {
+            Some((_, existing)) => {
+                *existing = keys;
+            }
+            None => actions.push((action, keys)),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/graph-tui/keys.toml`, falling back to `~/.config/graph-tui/keys.toml`.
+pub fn user_keymap_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("graph-tui").join("keys.toml"))
+}
+
+/// The user's keymap, or the defaults when there is no keys.toml.
+pub fn load_user_keymap() -> Result<Keymap, String> {
+    let Some(path) = user_keymap_path() else {
+        return Ok(Keymap::default());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => Keymap::from_toml_str(&raw).map_err(|error| {
+            format!("{}: {}", path.display(), error)
+        }),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Keymap::default()),
+        Err(error) => Err(format!("failed to read {}: {}", path.display(), error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn user_bindings_replace_defaults_and_steal_their_keys() {
+        let keymap = Keymap::from_toml_str(
+            r#"
+            [canvas]
+            relax = ["r", "Ctrl-l"]
+            fit = "Shift-z"
+
+            [form]
+            next_field = []
+            "#
+        ).unwrap();
+
+        let canvas = KeyContext::Canvas;
+        assert_eq!(keymap.action(canvas, &key(KeyCode::Char('r'))), Some(Action::Relax));
+        assert_eq!(keymap.action(canvas, &key(KeyCode::Char('R'))), None);
+        let ctrl_l = KeyEvent::new(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(canvas, &ctrl_l), Some(Action::Relax));
+        assert_eq!(keymap.action(canvas, &key(KeyCode::Char('l'))), Some(Action::PanRight));
+        assert_eq!(keymap.primary(canvas, Action::Fit).as_deref(), Some("Shift-Z"));
+        assert_eq!(keymap.primary(canvas, Action::Redo).as_deref(), Some("Shift-U"));
+        let exiting = KeyContext::Exiting;
+        assert_eq!(keymap.primary(exiting, Action::ExitNow).as_deref(), Some("Y"));
+        assert_eq!(keymap.prompt_key(exiting, Action::ExitNow).as_deref(), Some("y"));
+        assert_eq!(keymap.prompt_key(canvas, Action::Fit).as_deref(), Some("Z"));
+        assert_eq!(keymap.action(KeyContext::Form, &key(KeyCode::Tab)), None);
+        // Redo's Ctrl-r default is untouched by the lower-case `r` binding.
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(canvas, &ctrl_r), Some(Action::Redo));
+
+        let error = Keymap::from_toml_str("[canvas]\nfly = \"x\"").unwrap_err();
+        assert!(error.contains("unknown action 'fly'"), "{error}");
+        assert!(Keymap::from_toml_str("[canvas]\nfit = \"Hyper-x\"").is_err());
+        // Every screen keeps a way out, whether unbound directly or by taking its keys.
+        let error = Keymap::from_toml_str("[form]\ncancel = []").unwrap_err();
+        assert_eq!(error, "[form] cancel needs a key");
+        let stolen = "[exiting]\nexit_now = [\"n\", \"q\", \"Esc\"]";
+        assert_eq!(Keymap::from_toml_str(stolen).unwrap_err(), "[exiting] back needs a key");
+
+        let word_left: KeyBinding = "ctrl-left".parse().unwrap();
+        assert_eq!(word_left.to_string(), "Ctrl-Left");
+        let ctrl_left = KeyEvent::new(KeyCode::Left, KeyModifiers::CONTROL);
+        let form = KeyContext::Form;
+        assert_eq!(keymap.action(form, &ctrl_left), Some(Action::WordLeft));
+        assert_eq!(keymap.action(form, &key(KeyCode::Left)), Some(Action::CursorLeft));
+    }
+}
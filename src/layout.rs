@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use crate::analysis::flow_order;
+use crate::graph::Graph;
+use crate::history::Transaction;
+use crate::node_builder::NodeTypeCatalog;
+
+/// Horizontal distance between layers, in `coords` units.
+const LAYER_GAP: f64 = 120.0;
+/// Vertical distance between nodes of one layer.
+const ROW_GAP: f64 = 60.0;
+/// Barycenter passes (each one down and one up) used to reduce edge crossings.
+const SWEEPS: usize = 4;
+
+/// Left-to-right layered (Sugiyama-style) positions for every node, keyed by node id.
+///
+/// 1. Cycles are broken by reversing back edges, i.e. edges that point backwards in
+///    `flow_order`.
+/// 2. Each node goes one layer to the right of its furthest upstream neighbour.
+/// 3. Nodes within a layer are reordered by the mean position of their neighbours
+///    (barycenter heuristic), sweeping in both directions, to cut crossings.
+/// 4. Layers are spaced `LAYER_GAP` apart and centred on `y = 0`, `ROW_GAP` between rows.
+pub fn layered_layout(graph: &Graph) -> HashMap<usize, (f64, f64)> {
+    let n = graph.nodes.len();
+    let order = flow_order(graph);
+    let mut rank = vec![0; n];
+    for (position, &i) in order.iter().enumerate() {
+        rank[i] = position;
+    }
+    let index_of: HashMap<usize, usize> = graph.nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+
+    // Forward edges only: back edges are reversed, self-loops dropped.
+    let mut preds: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut succs: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for edge in &graph.edges {
+        let from = index_of.get(&(edge.from as usize));
+        let to = index_of.get(&(edge.to as usize));
+        let (Some(&from), Some(&to)) = (from, to) else {
+            continue;
+        };
+        if from == to {
+            continue;
+        }
+        let (from, to) = if rank[from] < rank[to] { (from, to) } else { (to, from) };
+        preds[to].push(from);
+        succs[from].push(to);
+    }
+
+    // Longest-path layering; `order` is a topological order of the forward edges.
+    let mut layer = vec![0; n];
+    for &i in &order {
+        layer[i] = preds[i]
+            .iter()
+            .map(|&p| layer[p] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    let layer_count = layer
+        .iter()
+        .max()
+        .map_or(0, |l| l + 1);
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); layer_count];
+    for &i in &order {
+        layers[layer[i]].push(i);
+    }
+
+    let mut slot = vec![0.0; n];
+    let reindex = |layers: &Vec<Vec<usize>>, slot: &mut Vec<f64>| {
+        for members in layers {
+            for (position, &i) in members.iter().enumerate() {
+                slot[i] = position as f64;
+            }
+        }
+    };
+    reindex(&layers, &mut slot);
+    for _ in 0..SWEEPS {
+        for l in 1..layer_count {
+            sort_by_barycenter(&mut layers[l], &preds, &slot);
+            reindex(&layers, &mut slot);
+        }
+        for l in (0..layer_count.saturating_sub(1)).rev() {
+            sort_by_barycenter(&mut layers[l], &succs, &slot);
+            reindex(&layers, &mut slot);
+        }
+    }
+
+    let mut positions = HashMap::new();
+    for (l, members) in layers.iter().enumerate() {
+        let offset = ((members.len() as f64) - 1.0) / 2.0;
+        for (row, &i) in members.iter().enumerate() {
+            let x = (l as f64) * LAYER_GAP;
+            let y = ((row as f64) - offset) * ROW_GAP;
+            positions.insert(graph.nodes[i].id, (x, y));
+        }
+    }
+    positions
+}
+
+/// Stable sort of one layer by the mean slot of each node's `neighbours`; nodes without
+/// neighbours keep their current slot.
+fn sort_by_barycenter(members: &mut [usize], neighbours: &[Vec<usize>], slot: &[f64]) {
+    let key = |i: usize| {
+        let around = &neighbours[i];
+        if around.is_empty() {
+            slot[i]
+        } else {
+            around
+                .iter()
+                .map(|&j| slot[j])
+                .sum::<f64>() / (around.len() as f64)
+        }
+    };
+    members.sort_by(|&a, &b| key(a).total_cmp(&key(b)));
+}
+
+/// Write `layered_layout` positions into `coords` through `tx`, returning how many nodes
+/// were placed.
+///
+/// Only nodes whose template type declares a `coords` table are touched, so nothing is
+/// written that the exporter would drop. Unless `all` is set, nodes that already have
+/// coordinates keep them and the new ones are laid out in a band below them.
+pub fn apply_layout(
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    catalog: &NodeTypeCatalog,
+    all: bool
+) -> usize {
+    let positions = layered_layout(graph);
+    let targets: Vec<usize> = graph.nodes
+        .iter()
+        .filter(|node| {
+            wants_coords(catalog, &node.type_) && (all || catalog.node_coords(node).is_none())
+        })
+        .map(|node| node.id)
+        .collect();
+
+    let (mut dx, mut dy) = (0.0, 0.0);
+    let placed: Vec<(f64, f64)> = graph.nodes
+        .iter()
+        .filter(|node| !targets.contains(&node.id))
+        .filter_map(|node| catalog.node_coords(node))
+        .collect();
+    if !placed.is_empty() {
+        let min_x = placed.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_y = placed.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let top = targets
+            .iter()
+            .filter_map(|id| positions.get(id))
+            .map(|p| p.1)
+            .fold(f64::INFINITY, f64::min);
+        if top.is_finite() {
+            dx = min_x;
+            dy = max_y + ROW_GAP * 2.0 - top;
+        }
+    }
+
+    let mut count = 0;
+    for id in targets {
+        let Some(&(x, y)) = positions.get(&id) else {
+            continue;
+        };
+        if tx.update_node(graph, id, |node| catalog.set_node_coords(node, x + dx, y + dy)) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Preferred edge length of the force-directed layout, in `coords` units.
+const IDEAL_DISTANCE: f64 = 80.0;
+/// Iterations a force-directed run takes to cool down completely.
+pub const FORCE_ITERATIONS: usize = 300;
+/// Seed used when none is given, so repeated runs produce the same picture.
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Seeded Fruchterman-Reingold layout that can be advanced one iteration at a time.
+///
+/// Every node repels every other, edges pull their ends together, and moves are capped
+/// by a temperature that cools linearly to zero over `FORCE_ITERATIONS` steps. Nodes
+/// start from their `coords`, or from seeded pseudo-random spots near the placed ones,
+/// so the same graph and seed always give the same result.
+#[derive(Debug)]
+pub struct ForceLayout {
+    ids: Vec<usize>,
+    pos: Vec<(f64, f64)>,
+    /// Placed nodes that must not move (when only missing coords are being filled).
+    pinned: Vec<bool>,
+    /// Nodes whose result is reported: those whose type declares `coords`.
+    writes: Vec<bool>,
+    edges: Vec<(usize, usize)>,
+    iteration: usize,
+}
+
+impl ForceLayout {
+    pub fn new(graph: &Graph, catalog: &NodeTypeCatalog, seed: u64, all: bool) -> Self {
+        let n = graph.nodes.len();
+        let index_of: HashMap<usize, usize> = graph.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id, i))
+            .collect();
+        let edges = graph.edges
+            .iter()
+            .filter_map(|edge| {
+                let from = *index_of.get(&(edge.from as usize))?;
+                let to = *index_of.get(&(edge.to as usize))?;
+                (from != to).then_some((from, to))
+            })
+            .collect();
+
+        let placed: Vec<(f64, f64)> = graph.nodes
+            .iter()
+            .filter_map(|node| catalog.node_coords(node))
+            .collect();
+        let center = if placed.is_empty() {
+            (0.0, 0.0)
+        } else {
+            let count = placed.len() as f64;
+            (
+                placed.iter().map(|p| p.0).sum::<f64>() / count,
+                placed.iter().map(|p| p.1).sum::<f64>() / count,
+            )
+        };
+        let spread = IDEAL_DISTANCE * (n.max(1) as f64).sqrt();
+        let mut rng = seed;
+        let mut pos = Vec::with_capacity(n);
+        let mut pinned = Vec::with_capacity(n);
+        let mut writes = Vec::with_capacity(n);
+        for node in &graph.nodes {
+            let wants_coords = wants_coords(catalog, &node.type_);
+            let coords = catalog.node_coords(node);
+            match coords {
+                Some(p) => pos.push(p),
+                None => {
+                    let x = center.0 + (next_unit(&mut rng) - 0.5) * spread;
+                    let y = center.1 + (next_unit(&mut rng) - 0.5) * spread;
+                    pos.push((x, y));
+                }
+            }
+            pinned.push(!all && wants_coords && coords.is_some());
+            writes.push(wants_coords && (all || coords.is_none()));
+        }
+
+        Self {
+            ids: graph.nodes
+                .iter()
+                .map(|node| node.id)
+                .collect(),
+            pos,
+            pinned,
+            writes,
+            edges,
+            iteration: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.iteration >= FORCE_ITERATIONS || self.writes.iter().all(|w| !w)
+    }
+
+    /// Advance one iteration; does nothing once the layout has cooled down.
+    pub fn step(&mut self) {
+        if self.is_done() {
+            return;
+        }
+        let n = self.pos.len();
+        let k = IDEAL_DISTANCE;
+        let temperature =
+            k * 2.0 * (1.0 - (self.iteration as f64) / (FORCE_ITERATIONS as f64));
+        let mut disp = vec![(0.0, 0.0); n];
+
+        for i in 0..n {
+            for j in i + 1..n {
+                let (mut dx, mut dy) = (
+                    self.pos[i].0 - self.pos[j].0,
+                    self.pos[i].1 - self.pos[j].1,
+                );
+                if dx == 0.0 && dy == 0.0 {
+                    // Coincident nodes: push apart along a fixed, index-derived direction.
+                    let angle = ((i * 31 + j * 17) as f64) * 0.618;
+                    (dx, dy) = (angle.cos() * 0.01, angle.sin() * 0.01);
+                }
+                let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+                let force = (k * k) / dist;
+                let (fx, fy) = ((dx / dist) * force, (dy / dist) * force);
+                disp[i].0 += fx;
+                disp[i].1 += fy;
+                disp[j].0 -= fx;
+                disp[j].1 -= fy;
+            }
+        }
+        for &(a, b) in &self.edges {
+            let (dx, dy) = (self.pos[a].0 - self.pos[b].0, self.pos[a].1 - self.pos[b].1);
+            let dist = (dx * dx + dy * dy).sqrt().max(0.01);
+            let force = (dist * dist) / k;
+            let (fx, fy) = ((dx / dist) * force, (dy / dist) * force);
+            disp[a].0 -= fx;
+            disp[a].1 -= fy;
+            disp[b].0 += fx;
+            disp[b].1 += fy;
+        }
+
+        for (i, &(dx, dy)) in disp.iter().enumerate() {
+            if self.pinned[i] {
+                continue;
+            }
+            let len = (dx * dx + dy * dy).sqrt();
+            if len > 0.0 {
+                let limited = len.min(temperature);
+                self.pos[i].0 += (dx / len) * limited;
+                self.pos[i].1 += (dy / len) * limited;
+            }
+        }
+        self.iteration += 1;
+    }
+
+    /// Current positions of the nodes this layout places, keyed by node id.
+    pub fn positions(&self) -> HashMap<usize, (f64, f64)> {
+        (0..self.ids.len())
+            .filter(|&i| self.writes[i])
+            .map(|i| (self.ids[i], self.pos[i]))
+            .collect()
+    }
+
+    /// Run to completion and return the final positions.
+    pub fn run(mut self) -> HashMap<usize, (f64, f64)> {
+        while !self.is_done() {
+            self.step();
+        }
+        self.positions()
+    }
+}
+
+/// Write a fully cooled `ForceLayout` into `coords` through `tx`, returning how many nodes
+/// were placed. Unless `all` is set, nodes that already have coordinates stay put.
+pub fn apply_force_layout(
+    graph: &mut Graph,
+    tx: &mut Transaction,
+    catalog: &NodeTypeCatalog,
+    seed: u64,
+    all: bool
+) -> usize {
+    let positions = ForceLayout::new(graph, catalog, seed, all).run();
+    let mut ids: Vec<usize> = positions.keys().copied().collect();
+    ids.sort();
+    let mut count = 0;
+    for id in ids {
+        let (x, y) = positions[&id];
+        if tx.update_node(graph, id, |node| catalog.set_node_coords(node, x, y)) {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn wants_coords(catalog: &NodeTypeCatalog, type_: &str) -> bool {
+    catalog.position_spec(type_).is_some()
+}
+
+/// SplitMix64 step mapped to `[0, 1)`.
+fn next_unit(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+    ((z >> 11) as f64) / ((1u64 << 53) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::load_node_catalog_default;
+    use crate::edge::Edge;
+    use crate::node_builder::NodeInstance;
+
+    #[test]
+    fn layers_follow_the_flow_and_only_fill_missing_coords() {
+        let catalog = load_node_catalog_default();
+        let mut g = Graph::new();
+        g.add_node(NodeInstance::new(1, "source".to_string(), "feed".to_string()));
+        for (id, label) in [(2, "t1"), (3, "t2"), (4, "t3")] {
+            g.add_node(NodeInstance::new(id, "cstr".to_string(), label.to_string()));
+        }
+        let mut placed = NodeInstance::new(5, "sensor".to_string(), "s".to_string());
+        catalog.set_node_coords(&mut placed, 500.0, 40.0);
+        g.add_node(placed);
+        // feed -> t1 -> t2 -> t3 -> t1 (recycle), t1 -> s
+        for (id, (from, to)) in [(1, 2), (2, 3), (3, 4), (4, 2), (2, 5)].into_iter().enumerate() {
+            g.add_edge(Edge { id: (id as u64) + 1, from, to, label: format!("s{}", id) });
+        }
+
+        let positions = layered_layout(&g);
+        let x = |id: usize| positions[&id].0;
+        assert_eq!([x(1), x(2), x(3), x(4)], [0.0, 120.0, 240.0, 360.0]);
+        assert_eq!(x(5), 240.0, "sensor sits one layer after t1");
+
+        let mut tx = Transaction::new("layout");
+        let count = apply_layout(&mut g, &mut tx, &catalog, false);
+        assert_eq!(count, 3, "the source has no coords param and the sensor is placed");
+        let coords = |id: usize| catalog.node_coords(g.get_node(id).unwrap());
+        assert_eq!(coords(1), None);
+        assert_eq!(coords(5), Some((500.0, 40.0)));
+        let (x2, y2) = coords(2).unwrap();
+        let (x3, _) = coords(3).unwrap();
+        assert_eq!(x3 - x2, LAYER_GAP);
+        assert!(y2 > 40.0, "new nodes go below the placed ones");
+    }
+
+    #[test]
+    fn force_layout_is_seeded_and_keeps_placed_nodes_pinned() {
+        let catalog = load_node_catalog_default();
+        let mut g = Graph::new();
+        let mut hub = NodeInstance::new(1, "virtual_sensor".to_string(), "hub".to_string());
+        catalog.set_node_coords(&mut hub, 0.0, 0.0);
+        g.add_node(hub);
+        for id in 2..=7 {
+            g.add_node(NodeInstance::new(id, "sensor".to_string(), format!("s{}", id)));
+            g.add_edge(Edge { id: id as u64, from: id as u64, to: 1, label: format!("m{}", id) });
+        }
+        g.add_node(NodeInstance::new(8, "sensor".to_string(), "loner".to_string()));
+
+        let first = ForceLayout::new(&g, &catalog, DEFAULT_SEED, false).run();
+        let again = ForceLayout::new(&g, &catalog, DEFAULT_SEED, false).run();
+        assert_eq!(first, again, "same seed, same layout");
+        let other = ForceLayout::new(&g, &catalog, 7, false).run();
+        assert_ne!(first, other);
+
+        assert!(!first.contains_key(&1), "placed hub is pinned, not reported");
+        let dist = |id: usize| {
+            let (x, y) = first[&id];
+            (x * x + y * y).sqrt()
+        };
+        for id in 2..=7 {
+            assert!(dist(id) < dist(8), "fed sensors gather around the hub: {first:?}");
+        }
+    }
+
+    #[test]
+    fn layout_writes_the_templates_position_table() {
+        let catalog: NodeTypeCatalog = toml
+            ::from_str(
+                "[nodes.types.tank.params.name]\ntype = \"string\"\nsource = \"node_label\"\n\n\
+                 [nodes.types.tank.params.pos]\ntype = \"table\"\nposition = true\n\
+                 fields = [\"left\", \"top\"]\n\n\
+                 [nodes.types.tank.params.coords]\ntype = \"string\"\n"
+            )
+            .expect("catalog parses");
+        let mut g = Graph::new();
+        for (id, label) in [(1, "a"), (2, "b")] {
+            g.add_node(NodeInstance::new(id, "tank".to_string(), label.to_string()));
+        }
+        g.add_edge(Edge { id: 1, from: 1, to: 2, label: "ab".to_string() });
+
+        let mut tx = Transaction::new("layout");
+        assert_eq!(apply_layout(&mut g, &mut tx, &catalog, false), 2);
+        let node = g.get_node(2).unwrap();
+        let pos = node.values["pos"].as_table().expect("position table");
+        assert!(pos.contains_key("left") && pos.contains_key("top"), "{pos:?}");
+        assert!(!node.values.contains_key("coords"));
+        assert_eq!(catalog.node_coords(node), Some((LAYER_GAP, 0.0)));
+    }
+}
@@ -1,42 +1,245 @@
-use clap::Parser;
+use clap::{ Parser, Subcommand };
+use color_eyre::eyre::eyre;
 
 mod app;
 mod tui;
 mod graph;
 mod node_builder;
 mod edge;
+mod canvas;
+mod fuzzy;
+mod history;
+mod validate;
+mod cli;
+mod dot;
+mod mermaid;
+mod csv_io;
+mod analysis;
+mod layout;
+mod keymap;
+mod command;
+mod input;
 
 #[derive(Debug, Parser)]
-#[command(name = "graph-tui", about = "Graph Tui for editing Graphs in the Terminal")]
+#[command(
+    name = "graph-tui",
+    about = "Graph Tui for editing Graphs in the Terminal",
+    args_conflicts_with_subcommands = true
+)]
 struct Args {
+    /// Run a headless command instead of opening the editor.
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long, value_name = "input_path")]
     template: Option<String>,
+    /// File to save to from inside the editor (defaults to the input file).
     #[arg(short, long, value_name = "output_path")]
     output: Option<String>,
+    /// Existing units file to open for editing.
+    #[arg(short, long, value_name = "graph_path")]
+    input: Option<String>,
+    /// Read and write units files as `toml` or `json` (default: by file extension).
+    #[arg(short, long, value_name = "format")]
+    format: Option<graph::GraphFormat>,
+    /// Emit units by `id` or in `flow` order (overrides the template's `unit_order`).
+    #[arg(long, value_name = "order")]
+    unit_order: Option<node_builder::UnitOrder>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check a units file against the template; exits non-zero on errors.
+    Validate {
+        #[arg(short, long, value_name = "template_path")]
+        template: Option<String>,
+        /// Read the units file as `toml` or `json` (default: by file extension).
+        #[arg(short, long, value_name = "format")]
+        format: Option<graph::GraphFormat>,
+        /// Units file to check.
+        #[arg(value_name = "graph_path")]
+        input: String,
+    },
+    /// Convert a units file into a diagram format.
+    Export {
+        #[arg(short, long, value_name = "template_path")]
+        template: Option<String>,
+        #[arg(short, long, value_enum)]
+        format: cli::ExportFormat,
+        /// Group nodes into subgraphs by dotted label prefix (mermaid).
+        #[arg(long)]
+        group: bool,
+        /// Emit units by `id` or in `flow` order (toml, json and csv).
+        #[arg(long, value_name = "order")]
+        unit_order: Option<node_builder::UnitOrder>,
+        /// Write here instead of stdout.
+        #[arg(short, long, value_name = "output_path")]
+        output: Option<String>,
+        /// Units file to convert.
+        #[arg(value_name = "graph_path")]
+        input: String,
+    },
+    /// Build a units file from spreadsheet CSV exports.
+    Import {
+        #[arg(short, long, value_name = "template_path")]
+        template: Option<String>,
+        /// Nodes table: id, type, label and one column per param.
+        #[arg(long, value_name = "nodes_csv")]
+        nodes: String,
+        /// Edges table: from, to, label.
+        #[arg(long, value_name = "edges_csv")]
+        edges: Option<String>,
+        /// Units file to write (TOML or JSON by extension).
+        #[arg(short, long, value_name = "output_path")]
+        output: String,
+        /// Write the units file as `toml` or `json` (default: by file extension).
+        #[arg(short, long, value_name = "format")]
+        format: Option<graph::GraphFormat>,
+        /// Emit units by `id` or in `flow` order.
+        #[arg(long, value_name = "order")]
+        unit_order: Option<node_builder::UnitOrder>,
+    },
+    /// Run a script of edit commands (`node`, `connect`, `set`, `rm`, `w`).
+    Run {
+        #[arg(short, long, value_name = "template_path")]
+        template: Option<String>,
+        /// Commands, one per line; `#` starts a comment line.
+        #[arg(short, long, value_name = "script_path")]
+        script: String,
+        /// Units file to start from instead of an empty graph.
+        #[arg(short, long, value_name = "graph_path")]
+        input: Option<String>,
+        /// Units file to write once the script has run (TOML or JSON by extension);
+        /// required unless the script has a `w`.
+        #[arg(short, long, value_name = "output_path")]
+        output: Option<String>,
+        /// Read and write units files as `toml` or `json` (default: by file extension).
+        #[arg(short, long, value_name = "format")]
+        format: Option<graph::GraphFormat>,
+        /// Emit units by `id` or in `flow` order.
+        #[arg(long, value_name = "order")]
+        unit_order: Option<node_builder::UnitOrder>,
+    },
+    /// Give nodes `coords` from a left-to-right layered (or force-directed) layout.
+    Layout {
+        #[arg(short, long, value_name = "template_path")]
+        template: Option<String>,
+        /// Re-lay out every node, not just those without coords.
+        #[arg(long)]
+        all: bool,
+        /// Use the force-directed layout instead of the layered one.
+        #[arg(long)]
+        force: bool,
+        /// Seed for `--force`; the same seed gives the same layout.
+        #[arg(long, value_name = "seed", requires = "force")]
+        seed: Option<u64>,
+        /// Write here instead of back to the input file.
+        #[arg(short, long, value_name = "output_path")]
+        output: Option<String>,
+        /// Read and write units files as `toml` or `json` (default: by file extension).
+        #[arg(short, long, value_name = "format")]
+        format: Option<graph::GraphFormat>,
+        /// Units file to lay out.
+        #[arg(value_name = "graph_path")]
+        input: String,
+    },
 }
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
     let args = Args::parse();
+    if let Some(command) = args.command {
+        return run_command(command);
+    }
+    // A broken template or keys.toml should not keep the editor from starting: fall back
+    // to the built-in one and say so in the status line.
+    let mut warnings = Vec::new();
     let template_path = args.template;
     let mut app = if let Some(path) = template_path {
         match app::load_node_catalog_from_path(&path) {
             Ok(catalog) => app::App::new_with_catalog(catalog),
-            Err(_) => app::App::new(),
+            Err(error) => {
+                warnings.push(format!("template {}: {}; using the built-in one", path, error));
+                app::App::new()
+            }
         }
     } else {
         app::App::new()
     };
-    ratatui::run(|terminal| tui::Tui::new().run(terminal, &mut app))?;
-    let result = app::write_graph_to_path(&args.output.unwrap(), &app.graph, &app.node_catalog);
-    match result {
-        Ok(()) => {
-            return Ok(());
+    if let Some(path) = &args.input {
+        match app::load_graph_from_path(path, args.format, &app.node_catalog) {
+            Ok(graph) => {
+                app.graph = graph;
+            }
+            Err(error) => {
+                return Err(eyre!("failed to load {}: {}", path, error));
+            }
         }
-        Err(error) => {
-            println!("{}", error.to_string());
+    }
+    // Saving happens inside the TUI; `-o` only picks where it goes (defaulting to `-i`).
+    app.file_path = args.output.or(args.input);
+    app.file_format = args.format;
+    if let Some(order) = args.unit_order {
+        app.node_catalog.set_unit_order(order);
+    }
+    match keymap::load_user_keymap() {
+        Ok(keymap) => {
+            app.keymap = keymap;
         }
+        Err(error) => warnings.push(format!("{}; using the default keys", error)),
+    }
+    if !warnings.is_empty() {
+        app.status = Some(warnings.join(" | "));
     }
+    ratatui::run(|terminal| tui::Tui::new().run(terminal, &mut app))?;
 
     Ok(())
 }
+
+fn run_command(command: Command) -> color_eyre::Result<()> {
+    match command {
+        Command::Validate { template, format, input } => {
+            let catalog = cli::load_catalog(template.as_deref()).map_err(|e| eyre!(e))?;
+            if !cli::run_validate(&input, format, &catalog).map_err(|e| eyre!(e))? {
+                std::process::exit(1);
+            }
+        }
+        Command::Export { template, format, group, unit_order, output, input } => {
+            let mut catalog = cli::load_catalog(template.as_deref()).map_err(|e| eyre!(e))?;
+            if let Some(order) = unit_order {
+                catalog.set_unit_order(order);
+            }
+            cli::run_export(&input, &catalog, format, group, output.as_deref()).map_err(|e|
+                eyre!(e)
+            )?;
+        }
+        Command::Import { template, nodes, edges, output, format, unit_order } => {
+            let mut catalog = cli::load_catalog(template.as_deref()).map_err(|e| eyre!(e))?;
+            if let Some(order) = unit_order {
+                catalog.set_unit_order(order);
+            }
+            let imported = cli::run_import(&nodes, edges.as_deref(), &output, format, &catalog);
+            if !imported.map_err(|e| eyre!(e))? {
+                std::process::exit(1);
+            }
+        }
+        Command::Run { template, script, input, output, format, unit_order } => {
+            let mut catalog = cli::load_catalog(template.as_deref()).map_err(|e| eyre!(e))?;
+            if let Some(order) = unit_order {
+                catalog.set_unit_order(order);
+            }
+            let (input, output) = (input.as_deref(), output.as_deref());
+            cli::run_edit_script(&script, input, output, format, &catalog).map_err(|e| {
+                eyre!(e)
+            })?;
+        }
+        Command::Layout { template, all, force, seed, output, format, input } => {
+            let catalog = cli::load_catalog(template.as_deref()).map_err(|e| eyre!(e))?;
+            let force = force.then(|| seed.unwrap_or(layout::DEFAULT_SEED));
+            let output = output.as_deref();
+            cli::run_layout(&input, output, all, force, format, &catalog).map_err(|e| {
+                eyre!(e)
+            })?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use std::collections::{ BTreeMap, BTreeSet, HashMap, HashSet };
+
+use crate::graph::Graph;
+use crate::node_builder::NodeInstance;
+
+/// Render `graph` as a Mermaid `flowchart LR`.
+///
+/// Node ids are derived from labels with everything outside `[A-Za-z0-9_]` replaced by
+/// `_` (suffixed with the node id until unique), and each node shows its label and
+/// type. Edges carry their stream name. With `group`, nodes whose label has a dotted
+/// prefix (`lane1` in `lane1.t2`) are wrapped in one `subgraph` per prefix.
+pub fn to_mermaid_string(graph: &Graph, group: bool) -> String {
+    let mut used = HashSet::new();
+    // Subgraphs share the id namespace with nodes, so they claim theirs first.
+    let prefixes: BTreeSet<&str> = graph.nodes
+        .iter()
+        .filter_map(|node| group_prefix(node, group))
+        .collect();
+    let group_ids: HashMap<&str, String> = prefixes
+        .into_iter()
+        .enumerate()
+        .map(|(index, prefix)| {
+            let id = format!("group_{}", sanitize(prefix));
+            (prefix, claim_id(&mut used, id, &(index + 1).to_string()))
+        })
+        .collect();
+    let ids = mermaid_ids(graph, &mut used);
+    let node_line = |node: &NodeInstance| {
+        format!(
+            "{}[\"{}<br/>{}\"]",
+            ids[&node.id],
+            escape(&node.label),
+            escape(&node.type_)
+        )
+    };
+
+    let mut out = String::from("flowchart LR\n");
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for node in &graph.nodes {
+        match group_prefix(node, group) {
+            Some(prefix) => groups.entry(prefix).or_default().push(node_line(node)),
+            None => out.push_str(&format!("    {}\n", node_line(node))),
+        }
+    }
+    for (prefix, lines) in groups {
+        out.push_str(&format!("    subgraph {}[\"{}\"]\n", group_ids[prefix], escape(prefix)));
+        for line in lines {
+            out.push_str(&format!("        {}\n", line));
+        }
+        out.push_str("    end\n");
+    }
+
+    for edge in &graph.edges {
+        let from = ids.get(&(edge.from as usize));
+        let to = ids.get(&(edge.to as usize));
+        let (Some(from), Some(to)) = (from, to) else {
+            continue;
+        };
+        if edge.label.is_empty() {
+            out.push_str(&format!("    {} --> {}\n", from, to));
+        } else {
+            out.push_str(&format!("    {} -->|\"{}\"| {}\n", from, escape(&edge.label), to));
+        }
+    }
+    out
+}
+
+/// The dotted prefix (`lane1` in `lane1.t2`) `node` is grouped under, if grouping.
+fn group_prefix(node: &NodeInstance, group: bool) -> Option<&str> {
+    match node.label.rsplit_once('.') {
+        Some((prefix, _)) if group && !prefix.is_empty() => Some(prefix),
+        _ => None,
+    }
+}
+
+fn mermaid_ids(graph: &Graph, used: &mut HashSet<String>) -> HashMap<usize, String> {
+    let mut ids = HashMap::new();
+    for node in &graph.nodes {
+        let mut id = sanitize(&node.label);
+        if id.is_empty() {
+            id = format!("n{}", node.id);
+        } else if id.starts_with(|c: char| c.is_ascii_digit()) {
+            id = format!("n{}", id);
+        }
+        ids.insert(node.id, claim_id(used, id, &node.id.to_string()));
+    }
+    ids
+}
+
+/// Append `_<suffix>` to `id` until it is unused, then mark it used.
+fn claim_id(used: &mut HashSet<String>, mut id: String, suffix: &str) -> String {
+    // `end` (any case) closes a subgraph in Mermaid.
+    while id.eq_ignore_ascii_case("end") || used.contains(&id) {
+        id = format!("{}_{}", id, suffix);
+    }
+    used.insert(id.clone());
+    id
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Escape text for a quoted Mermaid label.
+fn escape(value: &str) -> String {
+    value.replace('"', "#quot;").replace('<', "#lt;").replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+
+    #[test]
+    fn flowchart_sanitises_ids_and_groups_by_prefix() {
+        let mut g = Graph::new();
+        g.add_node(NodeInstance::new(1, "source".to_string(), "feed".to_string()));
+        g.add_node(NodeInstance::new(2, "cstr".to_string(), "lane1.t1".to_string()));
+        g.add_node(NodeInstance::new(3, "cstr".to_string(), "lane1_t1".to_string()));
+        g.add_edge(Edge { id: 1, from: 1, to: 2, label: "lane1_feed".to_string() });
+        g.add_edge(Edge { id: 2, from: 2, to: 3, label: "say \"hi\"".to_string() });
+
+        let flat = to_mermaid_string(&g, false);
+        assert_eq!(
+            flat,
+            "flowchart LR\n\
+             \x20   feed[\"feed<br/>source\"]\n\
+             \x20   lane1_t1[\"lane1.t1<br/>cstr\"]\n\
+             \x20   lane1_t1_3[\"lane1_t1<br/>cstr\"]\n\
+             \x20   feed -->|\"lane1_feed\"| lane1_t1\n\
+             \x20   lane1_t1 -->|\"say #quot;hi#quot;\"| lane1_t1_3\n"
+        );
+
+        let grouped = to_mermaid_string(&g, true);
+        assert!(
+            grouped.contains(
+                "    subgraph group_lane1[\"lane1\"]\n\
+                 \x20       lane1_t1[\"lane1.t1<br/>cstr\"]\n\
+                 \x20   end\n"
+            ),
+            "{grouped}"
+        );
+        assert!(grouped.contains("    feed[\"feed<br/>source\"]\n"), "{grouped}");
+    }
+
+    #[test]
+    fn suffixed_ids_never_collide_with_nodes_or_groups() {
+        let mut g = Graph::new();
+        for (id, label) in [(2, "a.b"), (5, "a_b_3"), (3, "a_b"), (4, "group_a"), (6, "x.y")] {
+            g.add_node(NodeInstance::new(id, "cstr".to_string(), label.to_string()));
+        }
+
+        let flat = to_mermaid_string(&g, false);
+        let ids: Vec<&str> = flat
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().split('[').next().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["a_b", "a_b_3", "a_b_3_3", "group_a", "x_y"]);
+
+        let grouped = to_mermaid_string(&g, true);
+        assert!(grouped.contains("    subgraph group_a[\"a\"]\n"), "{grouped}");
+        assert!(grouped.contains("    group_a_4[\"group_a<br/>cstr\"]\n"), "{grouped}");
+    }
+}
@@ -1,13 +1,14 @@
 use serde::{ Deserialize, Serialize };
 use std::collections::HashMap;
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NodeSchema {
     pub type_: String,
     pub params: HashMap<String, ParamDef>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct NodeInstance {
     pub id: usize,
     pub type_: String,
@@ -15,6 +16,7 @@ pub struct NodeInstance {
     pub values: HashMap<String, toml::Value>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Serialize)]
 pub struct TypeInstance {}
 
@@ -27,6 +29,42 @@ impl NodeInstance {
             values: HashMap::new(),
         }
     }
+
+    /// Position from the node's `spec.key` table, if both fields are numeric.
+    pub fn coords(&self, spec: &CoordsSpec) -> Option<(f64, f64)> {
+        let table = self.values.get(&spec.key)?.as_table()?;
+        let number = |key: &str| {
+            let value = table.get(key)?;
+            value.as_float().or_else(|| value.as_integer().map(|i| i as f64))
+        };
+        Some((number(&spec.x)?, number(&spec.y)?))
+    }
+
+    /// Write the position into the `spec.key` table, keeping any other keys it already has.
+    pub fn set_coords(&mut self, spec: &CoordsSpec, x: f64, y: f64) {
+        let mut table = match self.values.remove(&spec.key) {
+            Some(toml::Value::Table(table)) => table,
+            _ => toml::map::Map::new(),
+        };
+        table.insert(spec.x.clone(), toml::Value::Float(x));
+        table.insert(spec.y.clone(), toml::Value::Float(y));
+        self.values.insert(spec.key.clone(), toml::Value::Table(table));
+    }
+}
+
+/// Where a node keeps its position: a table param and the names of its x and y fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordsSpec {
+    pub key: String,
+    pub x: String,
+    pub y: String,
+}
+
+impl Default for CoordsSpec {
+    /// `coords = { x = .., y = .. }`, the shape of the bundled template.
+    fn default() -> Self {
+        Self { key: "coords".to_string(), x: "x".to_string(), y: "y".to_string() }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -46,6 +84,71 @@ pub struct FormatSpec {
     /// These are emitted verbatim from the template (no graph-derived data).
     #[serde(default)]
     pub tables: HashMap<String, toml::Value>,
+
+    /// Order of units in the serialized output (`"id"` or `"flow"`).
+    #[serde(default)]
+    pub unit_order: UnitOrder,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitOrder {
+    /// Grouped by type key, each group in node id order.
+    #[default]
+    Id,
+    /// Upstream to downstream along the edges, types interleaved (see `flow_order`).
+    Flow,
+}
+
+impl std::str::FromStr for UnitOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "id" => Ok(UnitOrder::Id),
+            "flow" => Ok(UnitOrder::Flow),
+            other => Err(format!("unknown unit order '{}' (expected id or flow)", other)),
+        }
+    }
+}
+
+impl NodeTypeCatalog {
+    pub fn unit_order(&self) -> UnitOrder {
+        self.format
+            .as_ref()
+            .map(|f| f.unit_order)
+            .unwrap_or_default()
+    }
+
+    /// Where nodes of `type_` keep their position, or `None` when the template gives the
+    /// type no position table (layout leaves those nodes alone).
+    pub fn position_spec(&self, type_: &str) -> Option<CoordsSpec> {
+        self.nodes.types.get(type_)?.position_spec()
+    }
+
+    /// `position_spec`, falling back to `coords.x`/`coords.y` so positions written by
+    /// hand on other types still show on the canvas.
+    pub fn coords_spec(&self, type_: &str) -> CoordsSpec {
+        self.position_spec(type_).unwrap_or_default()
+    }
+
+    pub fn node_coords(&self, node: &NodeInstance) -> Option<(f64, f64)> {
+        node.coords(&self.coords_spec(&node.type_))
+    }
+
+    pub fn set_node_coords(&self, node: &mut NodeInstance, x: f64, y: f64) {
+        node.set_coords(&self.coords_spec(&node.type_), x, y);
+    }
+
+    /// Override the template's `format.unit_order` (e.g. from the command line).
+    pub fn set_unit_order(&mut self, order: UnitOrder) {
+        let format = self.format.get_or_insert_with(|| FormatSpec {
+            root: "units".to_string(),
+            tables: HashMap::new(),
+            unit_order: UnitOrder::default(),
+        });
+        format.unit_order = order;
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -58,6 +161,40 @@ pub struct NodeTypeDef {
     #[serde(default)]
     pub order: Option<Vec<String>>,
     pub params: HashMap<String, ParamDef>,
+
+    /// Optional look of this type in diagram exports (ignored by the TOML output).
+    #[serde(default)]
+    pub style: Option<StyleHint>,
+}
+
+impl NodeTypeDef {
+    /// The `table` param marked `position = true`, else one named `coords`. Its `fields`
+    /// name the x and y keys (default `x` and `y`).
+    pub fn position_spec(&self) -> Option<CoordsSpec> {
+        let (key, def) = self.params
+            .iter()
+            .find(|(_, def)| def.position)
+            .or_else(|| self.params.get_key_value("coords"))?;
+        if !matches!(def.kind, ParamType::Table) {
+            return None;
+        }
+        let fields = def.fields.as_deref().unwrap_or_default();
+        let field = |index: usize, default: &str| {
+            fields.get(index).map_or(default, |name| name.as_str()).to_string()
+        };
+        Some(CoordsSpec { key: key.clone(), x: field(0, "x"), y: field(1, "y") })
+    }
+}
+
+/// Per-type diagram styling. Values are passed through as Graphviz attribute values.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct StyleHint {
+    /// Node shape, e.g. "box" or "ellipse".
+    pub shape: Option<String>,
+    /// Outline colour.
+    pub color: Option<String>,
+    /// Fill colour; makes the node filled.
+    pub fill: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -74,6 +211,14 @@ pub struct ParamDef {
     /// Optional hint controlling rendering (e.g. scalar vs list).
     #[serde(default)]
     pub render: Option<RenderHint>,
+
+    /// Marks the `table` param that holds the node's position (see `position_spec`).
+    #[serde(default)]
+    pub position: bool,
+
+    /// Keys of a `table` param; for the position, the x and y field names in that order.
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -1,7 +1,7 @@
 use std::io;
 
 use crossterm::event;
-use crossterm::event::{ Event, KeyEventKind };
+use crossterm::event::{ DisableBracketedPaste, EnableBracketedPaste, Event, KeyEventKind };
 use ratatui::{
     buffer::Buffer,
     layout::{ Constraint, Direction, Layout, Rect },
@@ -9,12 +9,27 @@ use ratatui::{
     style::Stylize,
     symbols::border,
     text::{ Line, Span, Text },
+    symbols::Marker,
     widgets::{ Block, Clear, Paragraph, Widget },
+    widgets::canvas::{ Canvas, Line as CanvasLine },
     DefaultTerminal,
     Frame,
 };
 
-use crate::app::{ App, CurrentScreen, CurrentlyEditing, EdgeEditorMode, InOut, NodeEditorMode };
+use crate::canvas::to_canvas;
+use crate::input::TextInput;
+use crate::keymap::{ Action, KeyContext };
+use crate::validate::{ describe_item, Severity };
+use crate::app::{
+    App,
+    CurrentScreen,
+    CurrentlyEditing,
+    EdgeEditorMode,
+    GraphItem,
+    InOut,
+    NodeEditorMode,
+    ParamStatus,
+};
 
 pub struct Tui;
 
@@ -24,10 +39,23 @@ impl Tui {
     }
 
     pub fn run(&mut self, terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+        // Pastes then arrive as one `Event::Paste` rather than as keys, so a pasted newline
+        // cannot submit a form halfway.
+        crossterm::execute!(io::stdout(), EnableBracketedPaste)?;
+        let result = self.event_loop(terminal, app);
+        crossterm::execute!(io::stdout(), DisableBracketedPaste)?;
+        result
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
         while !app.should_exit() {
+            let size = terminal.size()?;
+            // The canvas is drawn inside a bordered block.
+            app.viewport.resize(size.width.saturating_sub(2), size.height.saturating_sub(2));
+            app.refresh_loops();
             terminal.draw(|frame| Self::draw(app, frame))?;
             self.handle_events(app)?;
-            //app.on_tick();
+            app.on_tick();
         }
         Ok(())
     }
@@ -37,12 +65,13 @@ impl Tui {
     }
 
     fn handle_events(&mut self, app: &mut App) -> io::Result<()> {
-        if event::poll(std::time::Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    app.on_key(key);
-                }
-            }
+        if !event::poll(std::time::Duration::from_millis(16))? {
+            return Ok(());
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => app.on_key(key),
+            Event::Paste(text) => app.insert_text(&text),
+            _ => {}
         }
         Ok(())
     }
@@ -50,84 +79,195 @@ impl Tui {
 
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        match self.current_screen {
-            CurrentScreen::Main => render_main(self, area, buf),
-            CurrentScreen::Graph => render_graph(self, area, buf),
-            CurrentScreen::GraphEditor => render_graph_editor(self, area, buf),
-            CurrentScreen::NodeEditor => render_node_editor(self, area, buf),
-            CurrentScreen::EdgeEditor => render_edge_editor(self, area, buf),
-            CurrentScreen::Exiting => render_exiting(self, area, buf),
+        render_screen(self, self.current_screen, area, buf);
+    }
+}
+
+fn render_screen(app: &App, screen: CurrentScreen, area: Rect, buf: &mut Buffer) {
+    match screen {
+        CurrentScreen::Main => render_main(app, area, buf),
+        CurrentScreen::Graph => render_graph(app, area, buf),
+        CurrentScreen::GraphEditor => render_graph_editor(app, area, buf),
+        CurrentScreen::NodeEditor => render_node_editor(app, area, buf),
+        CurrentScreen::EdgeEditor => render_edge_editor(app, area, buf),
+        CurrentScreen::Canvas => render_canvas(app, area, buf),
+        CurrentScreen::SaveAs => render_save_as(app, area, buf),
+        CurrentScreen::Diagnostics => render_diagnostics(app, area, buf),
+        CurrentScreen::Palette => render_palette(app, area, buf),
+        CurrentScreen::CommandLine => render_command_line(app, area, buf),
+        CurrentScreen::Exiting => render_exiting(app, area, buf),
+    }
+}
+
+/// Footer spans for `items` (label, actions), showing the first key of each action as
+/// `<Key>` or `<A/B>`. Entries whose actions are all unbound are left out.
+fn key_hints(app: &App, context: KeyContext, items: &[(&str, &[Action])]) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    for (label, actions) in items {
+        let keys: Vec<String> = actions
+            .iter()
+            .filter_map(|action| app.keymap.primary(context, *action))
+            .collect();
+        if keys.is_empty() {
+            continue;
         }
+        spans.push(format!(" {} ", label).into());
+        spans.push(format!("<{}>", keys.join("/")).blue().bold());
+    }
+    if let Some(last) = spans.last_mut() {
+        last.content.to_mut().push(' ');
     }
+    spans
+}
+
+/// Title text with the open file and a `*` when there are unsaved changes.
+fn screen_title(app: &App, name: &str) -> String {
+    let file = match (&app.file_path, app.is_dirty()) {
+        (None, false) => String::new(),
+        (None, true) => "[unsaved]* ".to_string(),
+        (Some(path), dirty) => format!("[{}]{} ", path, if dirty { "*" } else { "" }),
+    };
+    format!(" {} {}", name, file)
 }
 
-fn render_main(_app: &App, area: Rect, buf: &mut Buffer) {
-    let title = Line::from(" Tui Graph Editor ".bold());
+fn render_main(app: &App, area: Rect, buf: &mut Buffer) {
+    let title = Line::from(screen_title(app, "Tui Graph Editor").bold());
     let instructions = Line::from(
-        vec![" Graph Editor ".into(), "<G>".blue().bold(), " Quit ".into(), "<Q> ".blue().bold()]
+        key_hints(
+            app,
+            KeyContext::Main,
+            &[
+                ("Graph Editor", &[Action::OpenGraph]),
+                ("Commands", &[Action::OpenPalette]),
+                ("Quit", &[Action::Quit]),
+            ]
+        )
     );
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
         .border_set(border::THICK);
 
-    let welcome_text = Text::from(
-        "Welcome to the Tui Graph Editor! Press 'G' to start editing your graph."
+    let open = app.keymap.prompt_key(KeyContext::Main, Action::OpenGraph).unwrap_or_default();
+    let mut welcome_text = Text::from(
+        format!("Welcome to the Tui Graph Editor! Press '{}' to start editing your graph.", open)
     );
+    if let Some(status) = &app.status {
+        welcome_text.push_line(Line::from(status.clone()).italic().dark_gray());
+    }
 
     Paragraph::new(welcome_text).centered().block(block).render(area, buf);
 }
 
 fn render_graph(app: &App, area: Rect, buf: &mut Buffer) {
-    let title = Line::from(" Tui Graph Editor: Graph Overview ".bold());
+    let title = Line::from(screen_title(app, "Tui Graph Editor: Graph Overview").bold());
     let instructions = Line::from(
-        vec![" Edit Graph ".into(), "<E>".blue().bold(), " Quit ".into(), "<Q> ".blue().bold()]
+        key_hints(
+            app,
+            KeyContext::Graph,
+            &[
+                ("Edit Graph", &[Action::EditGraph]),
+                ("Canvas", &[Action::OpenCanvas]),
+                ("Validate", &[Action::Validate]),
+                ("Save", &[Action::Save]),
+                ("Save As", &[Action::SaveAs]),
+                ("Commands", &[Action::OpenPalette]),
+                ("Quit", &[Action::Back]),
+            ]
+        )
     );
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
         .border_set(border::THICK);
 
-    let graph_text = Text::from(build_graph_lines(app));
+    let mut lines = build_graph_lines(app, None);
+    if let Some(status) = &app.status {
+        lines.insert(0, Line::from(status.clone()).italic().dark_gray());
+    }
+    lines.extend(build_loop_lines(app));
+    let graph_text = Text::from(lines);
 
     Paragraph::new(graph_text).block(block).render(area, buf);
 }
 
 fn render_graph_editor(app: &App, area: Rect, buf: &mut Buffer) {
-    let title = Line::from(" Tui Graph Editor: Graph ".bold());
+    let title = Line::from(screen_title(app, "Tui Graph Editor: Graph").bold());
     let instructions = Line::from(
-        vec![
-            " Add Node ".into(),
-            "<N>".blue().bold(),
-            " Add Edge ".into(),
-            "<E>".blue().bold(),
-            " Back ".into(),
-            "<Q> ".blue().bold()
-        ]
+        key_hints(
+            app,
+            KeyContext::Editor,
+            &[
+                ("Add Node", &[Action::AddNode]),
+                ("Add Edge", &[Action::AddEdge]),
+                ("Select", &[Action::Up, Action::Down]),
+                ("Edit Node", &[Action::Confirm]),
+                ("Delete", &[Action::Delete]),
+                ("Canvas", &[Action::OpenCanvas]),
+                ("Validate", &[Action::Validate]),
+                ("Undo", &[Action::Undo]),
+                ("Redo", &[Action::Redo]),
+                ("Save", &[Action::Save]),
+                ("Commands", &[Action::OpenPalette]),
+                ("Command Line", &[Action::CommandLine]),
+                ("Back", &[Action::Back]),
+            ]
+        )
     );
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
         .border_set(border::THICK);
 
-    let graph_text = Text::from(build_graph_lines(app));
+    let mut lines = build_graph_lines(app, app.selected_item());
+    if let Some(status) = &app.status {
+        lines.insert(0, Line::from(status.clone()).italic().dark_gray());
+    }
+    let graph_text = Text::from(lines);
     Paragraph::new(graph_text).block(block).render(area, buf);
 }
 
 fn render_node_editor(app: &App, area: Rect, buf: &mut Buffer) {
     render_graph_editor(app, area, buf);
 
-    let title = Line::from(" Add Node ".bold());
-    let instructions = Line::from(
-        vec![
-            " Type ".into(),
-            "<A..Z>".blue().bold(),
-            " Next/Save ".into(),
-            "<Enter>".blue().bold(),
-            " Cancel ".into(),
-            "<Esc> ".blue().bold()
-        ]
+    let title = if app.editing_node.is_some() {
+        Line::from(" Edit Node ".bold())
+    } else {
+        Line::from(" Add Node ".bold())
+    };
+    let reviewing = matches!(
+        app.currently_editing,
+        Some(CurrentlyEditing::Node(NodeEditorMode::Review))
     );
+    let instructions = if reviewing {
+        let confirm = if app.editing_node.is_some() { "Save" } else { "Create" };
+        Line::from(
+            key_hints(
+                app,
+                KeyContext::Form,
+                &[
+                    (confirm, &[Action::Confirm]),
+                    ("Select", &[Action::Up, Action::Down]),
+                    ("Edit", &[Action::NextField]),
+                    ("Cancel", &[Action::Cancel]),
+                ]
+            )
+        )
+    } else {
+        let mut keys = vec![" Type ".into(), "<A..Z>".blue().bold()];
+        keys.extend(
+            key_hints(
+                app,
+                KeyContext::Form,
+                &[
+                    ("Next", &[Action::Confirm]),
+                    ("Param", &[Action::Up, Action::Down, Action::NextField]),
+                    ("Cancel", &[Action::Cancel]),
+                ]
+            )
+        );
+        Line::from(keys)
+    };
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
@@ -137,39 +277,89 @@ fn render_node_editor(app: &App, area: Rect, buf: &mut Buffer) {
         Some(CurrentlyEditing::Node(NodeEditorMode::Label)) => true,
         Some(CurrentlyEditing::Node(NodeEditorMode::Param)) => false,
         Some(CurrentlyEditing::Node(NodeEditorMode::Type)) => false,
+        Some(CurrentlyEditing::Node(NodeEditorMode::Review)) => false,
         Some(CurrentlyEditing::Edge(_)) => false,
         None => false,
     };
 
+    let popup_area = centered_rect(80, 70, area);
+    // Inside the border, after "Label: " / "Value: ".
+    let input_width = popup_area.width.saturating_sub(9);
     let label_prefix = if label_active { "Label:".yellow().underlined() } else { "Label:".into() };
-    let label_value = app.label.clone().yellow();
+    let mut label_line = Line::from(vec![label_prefix, " ".into()]);
+    if label_active {
+        label_line.spans.extend(input_spans(&app.label, input_width, Style::new().yellow()));
+    } else {
+        label_line.spans.push(app.label.as_str().to_string().yellow());
+    }
 
     let mut lines = Vec::new();
 
     if matches!(&app.currently_editing, Some(CurrentlyEditing::Node(NodeEditorMode::Type))) {
         let type_name = app.current_type_name().unwrap_or("-");
-        lines.push(Line::from(vec![label_prefix, " ".into(), label_value]));
-        lines.push(Line::from(format!("Mode: type")));
+        lines.push(label_line);
+        lines.push(Line::from("Mode: type"));
         lines.push(Line::from(format!("Type: {}", type_name)));
         lines.push(Line::from("Use Up/Down to select, Enter to confirm."));
     } else if matches!(&app.currently_editing, Some(CurrentlyEditing::Node(NodeEditorMode::Label))) {
-        lines.push(Line::from(vec![label_prefix, " ".into(), label_value]));
+        lines.push(label_line);
         lines.push(Line::from(""));
         lines.push(Line::from("Enter label, then press Enter to continue."));
+    } else if reviewing {
+        lines.push(label_line);
+        lines.push(Line::from(format!("Type: {}", app.pending_node().type_)));
+        lines.push(Line::from(""));
+        let review = app.param_review();
+        if review.is_empty() {
+            lines.push(Line::from("No params").dark_gray());
+        }
+        // Editable params come first and line up with `param_keys`, so the edit index is
+        // the highlighted row; the editor's own params follow, unselectable.
+        let editable = app.node_edit.as_ref().map_or(0, |edit| edit.param_count());
+        let index = app.node_edit.as_ref().map_or(0, |edit| edit.index);
+        let review_line = |key: &str, status: &ParamStatus| {
+            let value = match status {
+                ParamStatus::Set(value) => value.clone().yellow(),
+                ParamStatus::Derived(value) => format!("{} (derived)", value).dark_gray(),
+                ParamStatus::Unset => "unset".dark_gray().italic(),
+            };
+            Line::from(vec![format!("  {} = ", key).into(), value])
+        };
+        // Keep the highlighted param visible when the list is longer than the popup.
+        let window = (popup_area.height as usize).saturating_sub(9).max(3);
+        let first = index.saturating_sub(window - 1);
+        let shown = review[..editable].iter().skip(first).take(window);
+        for (offset, (key, status)) in shown.enumerate() {
+            let line = review_line(key, status);
+            lines.push(if first + offset == index { line.reversed() } else { line });
+        }
+        if editable > first + window {
+            lines.push(Line::from(format!("  … {} more", editable - first - window)));
+        }
+        if editable < review.len() {
+            lines.push(Line::from("Set by the editor:").dark_gray());
+            for (key, status) in &review[editable..] {
+                lines.push(review_line(key, status).dark_gray());
+            }
+        }
     } else if let Some(edit) = &app.node_edit {
         let param_name = edit.current_key().unwrap_or("-");
         let param_type = edit
             .current_def()
             .map(|def| format!("{:?}", def.kind))
             .unwrap_or_else(|| "-".to_string());
-        lines.push(Line::from(vec![label_prefix, " ".into(), label_value]));
-        lines.push(Line::from(format!("Param: {}", param_name)));
+        lines.push(label_line);
+        lines.push(
+            Line::from(
+                format!("Param: {} ({}/{})", param_name, edit.index + 1, edit.param_count())
+            )
+        );
         lines.push(Line::from(format!("Type: {}", param_type)));
-        lines.push(render_param_input_line(edit.current_def(), &edit.buffer));
+        lines.push(render_param_input_line(edit.current_def(), &edit.buffer, input_width));
         let input_debug = if edit.buffer.is_empty() {
             "<empty>".to_string()
         } else {
-            edit.buffer.clone()
+            edit.buffer.as_str().to_string()
         };
         lines.push(Line::from(format!("Input: {}", input_debug)));
         if let Some(format_hint) = param_format_hint(edit.current_def()) {
@@ -178,13 +368,17 @@ fn render_node_editor(app: &App, area: Rect, buf: &mut Buffer) {
         if let Some(error) = &edit.error {
             lines.push(Line::from(format!("Error: {}", error)).red());
         }
+        lines.push(Line::from("Use Up/Down or Tab to jump between params."));
     }
 
     lines.push(Line::from(""));
-    lines.push(Line::from("Enter to advance/save, Esc to cancel, Q to go back."));
+    if reviewing {
+        lines.push(Line::from("Enter to confirm, Tab to edit the highlighted param."));
+    } else {
+        lines.push(Line::from("Enter to advance to the review, Esc to cancel."));
+    }
 
     let body = Text::from(lines);
-    let popup_area = centered_rect(80, 70, area);
     Clear.render(popup_area, buf);
     Paragraph::new(body).block(block).render(popup_area, buf);
 }
@@ -201,9 +395,20 @@ fn param_format_hint(def: Option<&crate::node_builder::ParamDef>) -> Option<&'st
     }
 }
 
+/// `input` with a cursor bar at its cursor, scrolled to fit in `width` columns.
+fn input_spans(input: &TextInput, width: u16, style: Style) -> Vec<Span<'static>> {
+    let (before, after) = input.visible(width as usize);
+    vec![
+        Span::styled(before.to_string(), style),
+        Span::styled("|", style.fg(Color::Red).bold()),
+        Span::styled(after.to_string(), style)
+    ]
+}
+
 fn render_param_input_line(
     def: Option<&crate::node_builder::ParamDef>,
-    buffer: &str
+    buffer: &TextInput,
+    width: u16
 ) -> Line<'static> {
     let value_label = Span::styled("Value:", Style::new().yellow().underlined());
     let (prefix, suffix) = match def.map(|def| &def.kind) {
@@ -211,34 +416,36 @@ fn render_param_input_line(
         Some(crate::node_builder::ParamType::Table) => ("{ ", " }"),
         _ => ("", ""),
     };
-    let placeholder = "<enter value>";
-    let input = if buffer.is_empty() { placeholder } else { buffer };
-    let input_style = if buffer.is_empty() {
-        Style::new().fg(Color::DarkGray).bg(Color::White)
+    let mut spans = vec![value_label, Span::raw(" "), Span::raw(prefix)];
+    if buffer.is_empty() {
+        let placeholder = Style::new().fg(Color::DarkGray).bg(Color::White);
+        spans.push(Span::styled("<enter value>", placeholder));
+        spans.push(Span::styled("|", Style::new().fg(Color::Red).bg(Color::White).bold()));
     } else {
-        Style::new().fg(Color::Black).bg(Color::White)
-    };
-    let input_span = Span::styled(input.to_string(), input_style);
-    let cursor = Span::styled("|", Style::new().fg(Color::Red).bg(Color::White).bold());
-    Line::from(
-        vec![value_label, Span::raw(" "), Span::raw(prefix), input_span, cursor, Span::raw(suffix)]
-    )
+        let width = width.saturating_sub((prefix.len() + suffix.len()) as u16);
+        let input_style = Style::new().fg(Color::Black).bg(Color::White);
+        spans.extend(input_spans(buffer, width, input_style));
+    }
+    spans.push(Span::raw(suffix));
+    Line::from(spans)
 }
 
 fn render_edge_editor(app: &App, area: Rect, buf: &mut Buffer) {
     render_graph_editor(app, area, buf);
 
     let title = Line::from(" Add Edge ".bold());
-    let instructions = Line::from(
-        vec![
-            " Type label ".into(),
-            "<A..Z>".blue().bold(),
-            " Next ".into(),
-            "<Enter>".blue().bold(),
-            " Cancel ".into(),
-            "<Esc> ".blue().bold()
-        ]
+    let mut keys = vec![" Type label ".into(), "<A..Z>".blue().bold()];
+    keys.extend(
+        key_hints(
+            app,
+            KeyContext::Form,
+            &[
+                ("Next", &[Action::Confirm]),
+                ("Cancel", &[Action::Cancel]),
+            ]
+        )
     );
+    let instructions = Line::from(keys);
     let block = Block::bordered()
         .title(title.centered())
         .title_bottom(instructions.centered())
@@ -257,27 +464,190 @@ fn render_edge_editor(app: &App, area: Rect, buf: &mut Buffer) {
     let label_prefix = if label_active { "Label:".yellow().bold() } else { "Label:".into() };
     let from_prefix = if from_active { "From:".yellow().bold() } else { "From:".into() };
     let to_prefix = if to_active { "To:".yellow().bold() } else { "To:".into() };
-    let label_value = app.label.clone().yellow();
+    let popup_area = centered_rect(70, 70, area);
+    let mut label_line = Line::from(vec![label_prefix, " ".into()]);
+    if label_active {
+        let width = popup_area.width.saturating_sub(9);
+        label_line.spans.extend(input_spans(&app.label, width, Style::new().yellow()));
+    } else {
+        label_line.spans.push(app.label.as_str().to_string().yellow());
+    }
 
-    let from_value = format!("{}", app.in_outs[0]).yellow();
-    let to_value = format!("{}", app.in_outs[1]).yellow();
+    let from_value = endpoint_text(app, app.in_outs[0]).yellow();
+    let to_value = endpoint_text(app, app.in_outs[1]).yellow();
 
-    let lines = vec![
+    let mut lines = vec![
         Line::from(""),
-        Line::from(vec![label_prefix, " ".into(), label_value]),
+        label_line,
         Line::from(vec![from_prefix, " ".into(), from_value]),
-        Line::from(vec![to_prefix, " ".into(), to_value]),
-        Line::from(""),
-        Line::from("Enter to advance/save, Esc to cancel, Q to go back.")
+        Line::from(vec![to_prefix, " ".into(), to_value])
     ];
 
+    if from_active || to_active {
+        lines.push(Line::from(""));
+        lines.push(
+            Line::from(vec![
+                Span::styled("Search:", Style::new().yellow().underlined()),
+                Span::raw(" "),
+                Span::raw(app.node_picker.query.clone()),
+                Span::styled("|", Style::new().fg(Color::Red).bold())
+            ])
+        );
+        let matches = app.node_picker.matches(&app.graph);
+        if matches.is_empty() {
+            lines.push(Line::from("No matching nodes").dark_gray());
+        }
+        // Keep the highlighted candidate visible when the list is longer than the popup.
+        let window = 8;
+        let first = app.node_picker.index.saturating_sub(window - 1);
+        for (offset, id) in matches.iter().skip(first).take(window).enumerate() {
+            let line = Line::from(format!("  {}", endpoint_text(app, *id as u64)));
+            if first + offset == app.node_picker.index {
+                lines.push(line.reversed());
+            } else {
+                lines.push(line);
+            }
+        }
+        if matches.len() > first + window {
+            lines.push(Line::from(format!("  … {} more", matches.len() - first - window)));
+        }
+    }
+    if let Some(error) = &app.edge_error {
+        lines.push(Line::from(format!("Error: {}", error)).red());
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from("Type to search nodes, Up/Down to choose, Enter to pick, Esc to cancel."));
+
     let body = Text::from(lines);
-    let popup_area = centered_rect(60, 40, area);
     Clear.render(popup_area, buf);
     Paragraph::new(body).block(block).render(popup_area, buf);
 }
 
-fn build_graph_lines(app: &App) -> Vec<Line<'static>> {
+fn endpoint_text(app: &App, id: u64) -> String {
+    match app.graph.get_node(id as usize) {
+        Some(node) => format!("{}: {} ({})", node.id, node.label, node.type_),
+        None => "-".to_string(),
+    }
+}
+
+fn render_canvas(app: &App, area: Rect, buf: &mut Buffer) {
+    let unplaced = app.graph.nodes
+        .iter()
+        .filter(|node| app.node_catalog.node_coords(node).is_none())
+        .count();
+    let mut title = screen_title(app, "Tui Graph Editor: Canvas");
+    if unplaced > 0 {
+        title.push_str(&format!("({} without coords) ", unplaced));
+    }
+    if app.move_tool.active {
+        title.push_str(
+            &format!(
+                "[move step {}{}] ",
+                app.move_tool.step,
+                if app.move_tool.snap { ", snap" } else { "" }
+            )
+        );
+    }
+    let title = Line::from(title.bold());
+    let instructions = Line::from(
+        key_hints(
+            app,
+            KeyContext::Canvas,
+            &[
+                ("Pan/Move", &[Action::PanLeft, Action::PanDown, Action::PanUp, Action::PanRight]),
+                ("Zoom", &[Action::ZoomIn, Action::ZoomOut]),
+                ("Fit", &[Action::Fit]),
+                ("Select", &[Action::SelectNext]),
+                ("Move Mode", &[Action::ToggleMove]),
+                ("Step", &[Action::ShrinkStep, Action::GrowStep]),
+                ("Snap", &[Action::ToggleSnap]),
+                ("Layout Missing/All", &[Action::LayoutMissing, Action::LayoutAll]),
+                if app.force_layout.is_some() {
+                    ("Stop Relaxing", &[Action::StopLayout])
+                } else {
+                    ("Relax", &[Action::Relax])
+                },
+                ("Undo", &[Action::Undo]),
+                ("Commands", &[Action::OpenPalette]),
+                ("Back", &[Action::Back]),
+            ]
+        )
+    );
+    let mut block = Block::bordered()
+        .title(title.centered())
+        .title_bottom(instructions.centered())
+        .border_set(border::THICK);
+    if let Some(status) = &app.status {
+        block = block.title(Line::from(format!(" {} ", status).italic()).right_aligned());
+    }
+
+    let (x_bounds, y_bounds) = app.viewport.bounds();
+    // Arrowhead size in world units, so it stays the same on screen at any zoom.
+    let head = app.viewport.scale * 1.5;
+    Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds(y_bounds)
+        .paint(|ctx| {
+            let (loop_nodes, loop_edges) = (&app.loops.nodes, &app.loops.edges);
+            for edge in &app.graph.edges {
+                let coords = |id: u64| {
+                    let node = app.graph.get_node(id as usize)?;
+                    app.node_catalog.node_coords(node)
+                };
+                let (from, to) = (coords(edge.from), coords(edge.to));
+                let (Some(from), Some(to)) = (from, to) else {
+                    continue;
+                };
+                let (x1, y1) = to_canvas(from.0, from.1);
+                let (x2, y2) = to_canvas(to.0, to.1);
+                let color = if loop_edges.contains(&edge.id) { LOOP_COLOR } else { Color::Gray };
+                ctx.draw(&CanvasLine::new(x1, y1, x2, y2, color));
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                let len = (dx * dx + dy * dy).sqrt();
+                if len > 0.0 {
+                    let (ux, uy) = (dx / len, dy / len);
+                    for side in [-1.0, 1.0] {
+                        let hx = x2 - head * (ux * 0.9 - side * uy * 0.5);
+                        let hy = y2 - head * (uy * 0.9 + side * ux * 0.5);
+                        ctx.draw(&CanvasLine::new(x2, y2, hx, hy, color));
+                    }
+                }
+                ctx.print((x1 + x2) / 2.0, (y1 + y2) / 2.0, edge.label.clone().dark_gray());
+            }
+            ctx.layer();
+            for node in &app.graph.nodes {
+                let Some((x, y)) = app.node_catalog.node_coords(node) else {
+                    continue;
+                };
+                let (x, y) = to_canvas(x, y);
+                let label = if app.canvas_selected == Some(node.id) {
+                    node.label.clone().bold().reversed()
+                } else {
+                    node.label.clone().bold()
+                };
+                let marker = if loop_nodes.contains(&node.id) {
+                    "↻ ".fg(LOOP_COLOR)
+                } else {
+                    "● ".yellow()
+                };
+                ctx.print(
+                    x,
+                    y,
+                    Line::from(vec![marker, label, format!(" ({})", node.type_).dark_gray()])
+                );
+            }
+        })
+        .render(area, buf);
+}
+
+/// Colour for nodes and streams that sit on a recycle loop.
+const LOOP_COLOR: Color = Color::Magenta;
+
+fn build_graph_lines(app: &App, selected: Option<GraphItem>) -> Vec<Line<'static>> {
+    let (loop_nodes, loop_edges) = (&app.loops.nodes, &app.loops.edges);
     let mut lines = Vec::new();
     lines.push(
         Line::from(format!("Nodes: {}  Edges: {}", app.graph.nodes.len(), app.graph.edges.len()))
@@ -288,7 +658,15 @@ fn build_graph_lines(app: &App) -> Vec<Line<'static>> {
     } else {
         lines.push(Line::from("Nodes:"));
         for node in &app.graph.nodes {
-            lines.push(Line::from(format!("- {}: {}", node.id, node.label)));
+            let mut line = Line::from(format!("- {}: {} ({})", node.id, node.label, node.type_));
+            if loop_nodes.contains(&node.id) {
+                line = Line::from(vec![line.spans.remove(0), " ↻".into()]).fg(LOOP_COLOR);
+            }
+            if selected == Some(GraphItem::Node(node.id)) {
+                lines.push(line.reversed());
+            } else {
+                lines.push(line);
+            }
         }
     }
 
@@ -297,13 +675,52 @@ fn build_graph_lines(app: &App) -> Vec<Line<'static>> {
     } else {
         lines.push(Line::from("Edges:"));
         for edge in &app.graph.edges {
-            lines.push(Line::from(format!("- {} -> {}: {}", edge.from, edge.to, edge.label)));
+            let mut line = Line::from(format!("- {} -> {}: {}", edge.from, edge.to, edge.label));
+            if loop_edges.contains(&edge.id) {
+                line = Line::from(vec![line.spans.remove(0), " ↻".into()]).fg(LOOP_COLOR);
+            }
+            if selected == Some(GraphItem::Edge(edge.id)) {
+                lines.push(line.reversed());
+            } else {
+                lines.push(line);
+            }
         }
     }
 
     lines
 }
 
+/// One line per recycle loop: its nodes' labels and the streams that close it.
+fn build_loop_lines(app: &App) -> Vec<Line<'static>> {
+    let loops = &app.loops.loops;
+    if loops.is_empty() {
+        return vec![Line::from("No loops")];
+    }
+    let mut lines = vec![Line::from(format!("Loops: {}", loops.len()))];
+    for (i, found) in loops.iter().enumerate() {
+        let labels: Vec<String> = found.nodes
+            .iter()
+            .filter_map(|id| app.graph.get_node(*id))
+            .map(|node| node.label.clone())
+            .collect();
+        let streams: Vec<String> = app.graph.edges
+            .iter()
+            .filter(|edge| found.edges.contains(&edge.id))
+            .map(|edge| edge.label.clone())
+            .collect();
+        lines.push(
+            Line::from(
+                vec![
+                    format!("- loop {}: ", i + 1).fg(LOOP_COLOR),
+                    labels.join(", ").into(),
+                    format!("  via {}", streams.join(", ")).dark_gray()
+                ]
+            )
+        );
+    }
+    lines
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let vertical = Layout::default()
         .direction(Direction::Vertical)
@@ -326,12 +743,203 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     horizontal[1]
 }
 
-fn render_exiting(_app: &App, area: Rect, buf: &mut Buffer) {
+fn render_save_as(app: &App, area: Rect, buf: &mut Buffer) {
+    render_screen(app, app.save_return, area, buf);
+
+    let title = Line::from(" Save As ".bold());
+    let instructions = Line::from(
+        key_hints(
+            app,
+            KeyContext::Form,
+            &[
+                ("Save", &[Action::Confirm]),
+                ("Cancel", &[Action::Cancel]),
+            ]
+        )
+    );
+    let block = Block::bordered()
+        .title(title.centered())
+        .title_bottom(instructions.centered())
+        .border_set(border::THICK);
+
+    let popup_area = centered_rect(70, 30, area);
+    let mut path_line = Line::from(
+        vec![Span::styled("Path:", Style::new().yellow().underlined()), Span::raw(" ")]
+    );
+    let width = popup_area.width.saturating_sub(8);
+    path_line.spans.extend(input_spans(&app.save_path_input, width, Style::new()));
+    let mut lines = vec![Line::from(""), path_line];
+    if let Some(status) = &app.status {
+        lines.push(Line::from(status.clone()).red());
+    }
+
+    Clear.render(popup_area, buf);
+    Paragraph::new(Text::from(lines)).block(block).render(popup_area, buf);
+}
+
+fn render_palette(app: &App, area: Rect, buf: &mut Buffer) {
+    render_screen(app, app.palette_return, area, buf);
+
+    let context = KeyContext::for_screen(app.palette_return);
+    let title = Line::from(" Commands ".bold());
+    let instructions = Line::from(
+        key_hints(
+            app,
+            KeyContext::Form,
+            &[
+                ("Run", &[Action::Confirm]),
+                ("Select", &[Action::Up, Action::Down]),
+                ("Cancel", &[Action::Cancel]),
+            ]
+        )
+    );
+    let block = Block::bordered()
+        .title(title.centered())
+        .title_bottom(instructions.centered())
+        .border_set(border::THICK);
+
+    let popup_area = centered_rect(60, 60, area);
+    let mut lines = vec![
+        Line::from(
+            vec![
+                Span::styled("> ", Style::new().yellow()),
+                Span::raw(app.palette.query.clone()),
+                Span::styled("|", Style::new().fg(Color::Red).bold())
+            ]
+        ),
+        Line::from("")
+    ];
+    let matches = app.palette_matches();
+    if matches.is_empty() {
+        lines.push(Line::from("No matching command.".italic().dark_gray()));
+    }
+    // Keep the selection in view: the border and the two header lines take four rows.
+    let rows = (popup_area.height as usize).saturating_sub(4).max(1);
+    let offset = app.palette.index.saturating_sub(rows - 1);
+    for (i, action) in matches.iter().enumerate().skip(offset).take(rows) {
+        let keys: Vec<String> = app.keymap
+            .keys(context, *action)
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        let keys = if keys.is_empty() { "unbound".to_string() } else { keys.join(", ") };
+        let mut line = Line::from(
+            vec![Span::raw(format!("{:<32}", action.title())), keys.blue().bold()]
+        );
+        if i == app.palette.index {
+            line = line.reversed();
+        }
+        lines.push(line);
+    }
+
+    Clear.render(popup_area, buf);
+    Paragraph::new(Text::from(lines)).block(block).render(popup_area, buf);
+}
+
+fn render_command_line(app: &App, area: Rect, buf: &mut Buffer) {
+    render_screen(app, app.command_return, area, buf);
+
+    let instructions = Line::from(
+        key_hints(
+            app,
+            KeyContext::Form,
+            &[
+                ("Run", &[Action::Confirm]),
+                ("Complete", &[Action::NextField]),
+                ("Cancel", &[Action::Cancel]),
+            ]
+        )
+    );
+    let mut block = Block::bordered()
+        .title_bottom(instructions.centered())
+        .border_set(border::THICK);
+    if let Some(status) = &app.status {
+        block = block.title(Line::from(format!(" {} ", status).italic()));
+    }
+
+    let height = area.height.min(3);
+    let bar = Rect::new(area.x, area.bottom() - height, area.width, height);
+    let mut line = Line::from(Span::styled(":", Style::new().yellow()));
+    let width = bar.width.saturating_sub(3);
+    line.spans.extend(input_spans(&app.command_input, width, Style::new()));
+    Clear.render(bar, buf);
+    Paragraph::new(line).block(block).render(bar, buf);
+}
+
+fn render_diagnostics(app: &App, area: Rect, buf: &mut Buffer) {
+    let title = Line::from(screen_title(app, "Tui Graph Editor: Diagnostics").bold());
+    let mut items: Vec<(&str, &[Action])> = vec![
+        ("Select", &[Action::Up, Action::Down]),
+        ("Jump To", &[Action::Confirm]),
+        ("Re-run", &[Action::Rerun])
+    ];
+    if app.pending_save.is_some() {
+        items.push(("Write Anyway", &[Action::WriteAnyway]));
+    }
+    items.push(("Commands", &[Action::OpenPalette]));
+    items.push(("Back", &[Action::Back]));
+    let keys = key_hints(app, KeyContext::Diagnostics, &items);
+    let block = Block::bordered()
+        .title(title.centered())
+        .title_bottom(Line::from(keys).centered())
+        .border_set(border::THICK);
+
+    let mut lines = Vec::new();
+    if let Some(status) = &app.status {
+        lines.push(Line::from(status.clone()).italic().dark_gray());
+    }
+    if app.diagnostics.is_empty() {
+        lines.push(Line::from("No problems found.".green()));
+    }
+    for (i, diagnostic) in app.diagnostics.iter().enumerate() {
+        let severity = match diagnostic.severity {
+            Severity::Error => diagnostic.severity.as_str().red().bold(),
+            Severity::Warning => diagnostic.severity.as_str().yellow().bold(),
+        };
+        let target = describe_item(&app.graph, diagnostic.item);
+        let mut line = Line::from(
+            vec![
+                severity,
+                Span::raw(format!(" {}: ", target)),
+                Span::raw(diagnostic.message.clone())
+            ]
+        );
+        if i == app.diagnostics_index {
+            line = line.reversed();
+        }
+        lines.push(line);
+    }
+
+    Paragraph::new(Text::from(lines)).block(block).render(area, buf);
+}
+
+fn render_exiting(app: &App, area: Rect, buf: &mut Buffer) {
     let title = Line::from("Exiting".bold());
     let block = Block::bordered().title(title.centered()).border_set(border::THICK);
-    let counter_text = Text::from(
-        vec![Line::from(vec!["Do You Wish to Exit: ".into(), "y/n".yellow()])]
-    );
+    let key = |action| app.keymap.prompt_key(KeyContext::Exiting, action).unwrap_or_default();
+    let counter_text = if app.is_dirty() {
+        let target = app.file_path.as_deref().unwrap_or("a new file");
+        Text::from(
+            vec![
+                Line::from("You have unsaved changes!".red().bold()),
+                Line::from(
+                    vec![
+                        "Save to ".into(),
+                        target.to_string().yellow(),
+                        " and exit ".into(),
+                        key(Action::SaveAndExit).yellow(),
+                        ", discard and exit ".into(),
+                        key(Action::ExitNow).yellow(),
+                        ", cancel ".into(),
+                        key(Action::Back).yellow()
+                    ]
+                )
+            ]
+        )
+    } else {
+        let answers = format!("{}/{}", key(Action::ExitNow), key(Action::Back));
+        Text::from(vec![Line::from(vec!["Do You Wish to Exit: ".into(), answers.yellow()])])
+    };
     Paragraph::new(counter_text).centered().block(block).render(area, buf);
 }
 
@@ -350,17 +958,46 @@ mod tests {
         let mut expected = Buffer::with_lines(
             vec![
                 "┏━━━━━━━━━━━━━━━ Tui Graph Editor ━━━━━━━━━━━━━━━┓",
-                "┃Welcome to the Tui Graph Editor! Press 'G' to st┃",
+                "┃Welcome to the Tui Graph Editor! Press 'g' to st┃",
                 "┃                                                ┃",
-                "┗━━━━━━━━━━ Graph Editor <G> Quit <Q> ━━━━━━━━━━━┛"
+                "┗━ Graph Editor <G> Commands <Ctrl-P> Quit <Q> ━━┛"
             ]
         );
         let title_style = Style::new().bold();
         let key_style = Style::new().blue().bold();
         expected.set_style(Rect::new(16, 0, 18, 1), title_style);
-        expected.set_style(Rect::new(25, 3, 3, 1), key_style);
-        expected.set_style(Rect::new(34, 3, 4, 1), key_style);
+        expected.set_style(Rect::new(16, 3, 3, 1), key_style);
+        expected.set_style(Rect::new(29, 3, 8, 1), key_style);
+        expected.set_style(Rect::new(43, 3, 4, 1), key_style);
 
         assert_eq!(buf, expected);
     }
+
+    #[test]
+    fn canvas_places_nodes_at_their_coords() {
+        use crate::node_builder::NodeInstance;
+
+        let mut app = App::new();
+        for (id, label, x) in [(1, "left", 0.0), (2, "right", 100.0)] {
+            let mut node = NodeInstance::new(id, "cstr".to_string(), label.to_string());
+            let mut coords = toml::map::Map::new();
+            coords.insert("x".to_string(), toml::Value::Float(x));
+            coords.insert("y".to_string(), toml::Value::Float(0.0));
+            node.values.insert("coords".to_string(), toml::Value::Table(coords));
+            app.graph.add_node(node);
+        }
+        app.current_screen = CurrentScreen::Canvas;
+        app.viewport.resize(58, 10);
+        app.viewport.fit(&[(0.0, 0.0), (100.0, 0.0)]);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 60, 12));
+        app.render(buf.area, &mut buf);
+
+        let row = |y: u16| -> String {
+            (0..buf.area.width).map(|x| buf[(x, y)].symbol().to_string()).collect()
+        };
+        let middle = (0..buf.area.height).map(row).find(|line| line.contains("left"));
+        let middle = middle.expect("left node is drawn");
+        assert!(middle.find("left") < middle.find("right"), "{middle}");
+    }
 }
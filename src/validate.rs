@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+
+use crate::app::GraphItem;
+use crate::graph::Graph;
+use crate::node_builder::{
+    NodeInstance,
+    NodeTypeCatalog,
+    NodeTypeDef,
+    ParamDef,
+    ParamSource,
+    ParamType,
+    RenderHint,
+    ValueType,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// One problem found by `validate`, attached to the node or edge it concerns.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub item: GraphItem,
+    pub message: String,
+}
+
+pub fn error_count(diagnostics: &[Diagnostic]) -> usize {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count()
+}
+
+/// Check `graph` against the template in `catalog`.
+///
+/// Diagnostics come back ordered by severity (errors first), then nodes before edges,
+/// each in id order.
+pub fn validate(graph: &Graph, catalog: &NodeTypeCatalog) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    check_edges(graph, &mut out);
+    check_duplicate_labels(graph, &mut out);
+    for node in &graph.nodes {
+        match catalog.nodes.types.get(node.type_.as_str()) {
+            Some(type_def) => {
+                check_connections(graph, node, type_def, &mut out);
+                check_values(node, type_def, &mut out);
+            }
+            None => {
+                out.push(Diagnostic {
+                    severity: Severity::Error,
+                    item: GraphItem::Node(node.id),
+                    message: format!("unknown type '{}' (not in the template)", node.type_),
+                });
+            }
+        }
+    }
+    out.sort_by_key(|d| {
+        let item = match d.item {
+            GraphItem::Node(id) => (0, id as u64),
+            GraphItem::Edge(id) => (1, id),
+        };
+        (d.severity, item)
+    });
+    out
+}
+
+/// Human-readable name of a diagnostic's target, e.g. `node 3 'lane1.t1' (cstr)`.
+pub fn describe_item(graph: &Graph, item: GraphItem) -> String {
+    match item {
+        GraphItem::Node(id) =>
+            match graph.get_node(id) {
+                Some(node) => format!("node {} '{}' ({})", id, node.label, node.type_),
+                None => format!("node {}", id),
+            }
+        GraphItem::Edge(id) =>
+            match graph.edges.iter().find(|e| e.id == id) {
+                Some(edge) => format!("edge {} '{}'", id, edge.label),
+                None => format!("edge {}", id),
+            }
+    }
+}
+
+fn check_edges(graph: &Graph, out: &mut Vec<Diagnostic>) {
+    for edge in &graph.edges {
+        for (end, id) in [("from", edge.from), ("to", edge.to)] {
+            if graph.get_node(id as usize).is_none() {
+                out.push(Diagnostic {
+                    severity: Severity::Error,
+                    item: GraphItem::Edge(edge.id),
+                    message: format!("'{}' endpoint points at missing node {}", end, id),
+                });
+            }
+        }
+        if edge.label.trim().is_empty() {
+            out.push(Diagnostic {
+                severity: Severity::Error,
+                item: GraphItem::Edge(edge.id),
+                message: "edge has no stream label".to_string(),
+            });
+        }
+    }
+
+    // A stream may fan out to several consumers, but only one node may produce it.
+    let mut producers: HashMap<&str, Vec<u64>> = HashMap::new();
+    for edge in &graph.edges {
+        if edge.label.trim().is_empty() {
+            continue;
+        }
+        let entry = producers.entry(edge.label.as_str()).or_default();
+        if !entry.contains(&edge.from) {
+            entry.push(edge.from);
+        }
+    }
+    for edge in &graph.edges {
+        let Some(from) = producers.get(edge.label.as_str()) else {
+            continue;
+        };
+        if from.len() > 1 {
+            out.push(Diagnostic {
+                severity: Severity::Error,
+                item: GraphItem::Edge(edge.id),
+                message: format!(
+                    "stream '{}' is produced by several nodes ({})",
+                    edge.label,
+                    join_ids(from)
+                ),
+            });
+        }
+    }
+}
+
+fn check_duplicate_labels(graph: &Graph, out: &mut Vec<Diagnostic>) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for node in &graph.nodes {
+        if node.label.trim().is_empty() {
+            out.push(Diagnostic {
+                severity: Severity::Warning,
+                item: GraphItem::Node(node.id),
+                message: "node has no label".to_string(),
+            });
+            continue;
+        }
+        match seen.get(node.label.as_str()) {
+            Some(first) => {
+                out.push(Diagnostic {
+                    severity: Severity::Error,
+                    item: GraphItem::Node(node.id),
+                    message: format!("duplicate label '{}' (also node {})", node.label, first),
+                });
+            }
+            None => {
+                seen.insert(node.label.as_str(), node.id);
+            }
+        }
+    }
+}
+
+/// Compare the streams attached to `node` with the single-stream (`len = 1`) ports its
+/// template declares via `incoming_edge_label`/`outgoing_edge_label` sources. Streams are
+/// counted by label, so one stream fanning out to several consumers fills one port.
+fn check_connections(
+    graph: &Graph,
+    node: &NodeInstance,
+    type_def: &NodeTypeDef,
+    out: &mut Vec<Diagnostic>
+) {
+    let streams = |attached: &dyn Fn(&crate::edge::Edge) -> bool| {
+        let mut labels: Vec<&str> = graph.edges
+            .iter()
+            .filter(|e| attached(e))
+            .map(|e| e.label.as_str())
+            .collect();
+        labels.sort();
+        labels.dedup();
+        labels.len()
+    };
+    let incoming = streams(&|e| e.to == (node.id as u64));
+    let outgoing = streams(&|e| e.from == (node.id as u64));
+
+    for (side, count) in [("incoming", incoming), ("outgoing", outgoing)] {
+        let mut ports: Vec<(usize, &String, &ParamDef)> = Vec::new();
+        let mut unbounded = false;
+        for (key, def) in &type_def.params {
+            match (&def.source, side) {
+                (Some(ParamSource::IncomingEdgeLabels), "incoming")
+                | (Some(ParamSource::OutgoingEdgeLabels), "outgoing") => {
+                    unbounded = true;
+                }
+                (Some(ParamSource::IncomingEdgeLabel { index }), "incoming")
+                | (Some(ParamSource::OutgoingEdgeLabel { index }), "outgoing") if
+                    def.len == Some(1)
+                => {
+                    ports.push((index.unwrap_or(0), key, def));
+                }
+                _ => {}
+            }
+        }
+        if ports.is_empty() {
+            continue;
+        }
+        ports.sort_by_key(|(index, key, _)| (*index, (*key).clone()));
+
+        let capacity = ports
+            .iter()
+            .map(|(index, _, _)| index + 1)
+            .max()
+            .unwrap_or(0);
+        if !unbounded && count > capacity {
+            let keys: Vec<&str> = ports
+                .iter()
+                .map(|(_, key, _)| key.as_str())
+                .collect();
+            out.push(Diagnostic {
+                severity: Severity::Error,
+                item: GraphItem::Node(node.id),
+                message: format!(
+                    "has {} {} streams but `{}` takes {}",
+                    count,
+                    side,
+                    keys.join("`, `"),
+                    capacity
+                ),
+            });
+        }
+        for (index, key, _) in &ports {
+            if *index < count {
+                continue;
+            }
+            match node.values.get(key.as_str()) {
+                Some(value) => {
+                    out.push(Diagnostic {
+                        severity: Severity::Warning,
+                        item: GraphItem::Node(node.id),
+                        message: format!(
+                            "`{}` has no {} edge; using explicit value {}",
+                            key,
+                            side,
+                            crate::graph::render_inline_value(value).unwrap_or_default()
+                        ),
+                    });
+                }
+                // Sources and sinks legitimately end a line, so an open port is only
+                // worth a warning.
+                None => {
+                    out.push(Diagnostic {
+                        severity: Severity::Warning,
+                        item: GraphItem::Node(node.id),
+                        message: format!("`{}` is not connected to any {} edge", key, side),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn check_values(node: &NodeInstance, type_def: &NodeTypeDef, out: &mut Vec<Diagnostic>) {
+    let mut keys: Vec<&String> = node.values.keys().collect();
+    keys.sort();
+    for key in keys {
+        let value = &node.values[key];
+        let Some(def) = type_def.params.get(key.as_str()) else {
+            out.push(Diagnostic {
+                severity: Severity::Warning,
+                item: GraphItem::Node(node.id),
+                message: format!("`{}` is not a {} param and will not be emitted", key, node.type_),
+            });
+            continue;
+        };
+        if let Err(message) = check_value(value, def) {
+            out.push(Diagnostic {
+                severity: Severity::Error,
+                item: GraphItem::Node(node.id),
+                message: format!("`{}`: {}", key, message),
+            });
+        }
+    }
+}
+
+/// Check a stored value against its `ParamDef`, mirroring what `parse_param_value` accepts.
+pub fn check_value(value: &toml::Value, def: &ParamDef) -> Result<(), String> {
+    match def.kind {
+        ParamType::String if !value.is_str() => Err(expected("string", value)),
+        ParamType::Float if !(value.is_float() || value.is_integer()) => {
+            Err(expected("float", value))
+        }
+        ParamType::Bool if !value.is_bool() => Err(expected("bool", value)),
+        ParamType::List => {
+            // Lists rendered as scalars (len = 1 or `render = "scalar"`) may be stored bare.
+            let scalar_ok =
+                def.len == Some(1) || matches!(def.render, Some(RenderHint::Scalar));
+            let items: Vec<&toml::Value> = match value {
+                toml::Value::Array(items) => items.iter().collect(),
+                other if scalar_ok && !other.is_table() => vec![other],
+                other => {
+                    return Err(expected("list", other));
+                }
+            };
+            if let Some(len) = def.len && items.len() != len {
+                return Err(format!("expected list length {}, found {}", len, items.len()));
+            }
+            check_items(items, def.value_type.as_ref())
+        }
+        ParamType::Table => {
+            let Some(table) = value.as_table() else {
+                return Err(expected("table", value));
+            };
+            check_items(table.values().collect(), def.value_type.as_ref())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_items(items: Vec<&toml::Value>, value_type: Option<&ValueType>) -> Result<(), String> {
+    let Some(value_type) = value_type else {
+        return Ok(());
+    };
+    for item in items {
+        let ok = match value_type {
+            ValueType::String => item.is_str(),
+            ValueType::Float => item.is_float() || item.is_integer(),
+            ValueType::Bool => item.is_bool(),
+            ValueType::Any => true,
+        };
+        if !ok {
+            let name = match value_type {
+                ValueType::String => "string",
+                ValueType::Float => "float",
+                ValueType::Bool => "bool",
+                ValueType::Any => "any",
+            };
+            return Err(format!("entries must be {} values, found {}", name, item.type_str()));
+        }
+    }
+    Ok(())
+}
+
+fn expected(kind: &str, value: &toml::Value) -> String {
+    format!("expected {}, found {}", kind, value.type_str())
+}
+
+fn join_ids(ids: &[u64]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::load_node_catalog_default;
+    use crate::edge::Edge;
+
+    fn messages(diagnostics: &[Diagnostic], item: GraphItem) -> Vec<String> {
+        diagnostics
+            .iter()
+            .filter(|d| d.item == item)
+            .map(|d| format!("{}: {}", d.severity.as_str(), d.message))
+            .collect()
+    }
+
+    #[test]
+    fn reports_template_violations_per_node_and_edge() {
+        let catalog = load_node_catalog_default();
+        let mut g = Graph::new();
+        let mut t1 = NodeInstance::new(1, "cstr".to_string(), "t1".to_string());
+        t1.values.insert("in".to_string(), toml::Value::String("feed".to_string()));
+        t1.values.insert("parameter_tau".to_string(), toml::Value::String("fast".to_string()));
+        g.add_node(t1);
+        g.add_node(NodeInstance::new(2, "sensor".to_string(), "t1".to_string()));
+        g.add_node(NodeInstance::new(3, "sensor".to_string(), "s3".to_string()));
+        g.add_node(NodeInstance::new(4, "pump".to_string(), "p".to_string()));
+        g.add_edge(Edge { id: 1, from: 1, to: 2, label: "t1_out".to_string() });
+        g.add_edge(Edge { id: 2, from: 1, to: 3, label: "t1_out".to_string() });
+        g.add_edge(Edge { id: 3, from: 3, to: 0, label: "lost".to_string() });
+        g.add_edge(Edge { id: 4, from: 1, to: 3, label: "t1_vent".to_string() });
+
+        let diagnostics = validate(&g, &catalog);
+
+        let t1 = messages(&diagnostics, GraphItem::Node(1));
+        assert!(t1.iter().any(|m| m.starts_with("error: has 2 outgoing streams")), "{t1:?}");
+        assert!(t1.iter().any(|m| m.contains("`parameter_tau`: expected float")), "{t1:?}");
+        assert!(t1.iter().any(|m| m.starts_with("warning: `in` has no incoming edge")), "{t1:?}");
+
+        let n2 = messages(&diagnostics, GraphItem::Node(2));
+        assert!(n2.iter().any(|m| m.contains("duplicate label 't1'")), "{n2:?}");
+        let open = "warning: `out` is not connected to any outgoing edge";
+        assert!(n2.iter().any(|m| m == open), "a sink's open port only warns: {n2:?}");
+
+        // One stream fanning out to two consumers fills a single `out`.
+        g.remove_edge(4);
+        let diagnostics = validate(&g, &catalog);
+        let t1 = messages(&diagnostics, GraphItem::Node(1));
+        assert!(!t1.iter().any(|m| m.contains("outgoing streams")), "{t1:?}");
+
+        let pump = messages(&diagnostics, GraphItem::Node(4));
+        assert_eq!(pump, vec!["error: unknown type 'pump' (not in the template)"]);
+
+        let lost = messages(&diagnostics, GraphItem::Edge(3));
+        assert_eq!(lost, vec!["error: 'to' endpoint points at missing node 0"]);
+
+        // Fan-out of one producer's stream is fine; only the counts above complain.
+        assert!(messages(&diagnostics, GraphItem::Edge(1)).is_empty());
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+}